@@ -0,0 +1,159 @@
+use std::hint;
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use crate::Particle;
+
+// Lock-free particle storage. Each particle's position is packed into a single
+// AtomicU64: the bits of x in the low half and the bits of y in the high half.
+//
+// Packing matters: were x and y kept in two atomics, a reader could load x
+// before a store and y after it, and see a position that was never stored.
+//
+// Memory ordering: every load and store is Relaxed. Each particle is one
+// atomic location, so this still guarantees that
+//   - a load never sees x from one store and y from another (no torn reads)
+//   - loads of one particle never go back to an older value once a newer one
+//     has been seen (per-location coherence)
+// It does not order different particles against each other, so a pass over
+// the store while movers run can see some particles before a move and others
+// after it. Callers that need a consistent step must synchronise themselves,
+// e.g. with a barrier or a thread join, which also makes every earlier store visible.
+//
+// In 3D, z does not fit in the same word. It is kept beside it and guarded by
+// a per-particle sequence number (a seqlock): a store makes the number odd,
+// writes x, y and z, then makes it even again, and a load retries until it
+// reads the same even number before and after. Loads still never see a mix of
+// two stores, but may spin while a store is in progress. Each particle must
+// only be stored by one thread at a time.
+//
+// Only positions are shared. Everything else about a particle (id, velocity,
+// mass, radius) is loaded as it was when copied in, so a thread that moves a
+// particle should keep its own copy and store the position after each move.
+pub struct AtomicParticles {
+    particles: Vec<Particle>, // The particles as copied in; only read after that
+    positions: Vec<AtomicU64>,
+    depths: Option<Vec<Depth>>, // z for each particle, in 3D only
+    removed: Vec<AtomicBool>,   // Set once a particle has left the system
+}
+
+// The z coordinate of a particle and the sequence number guarding it
+struct Depth {
+    z: AtomicU32,
+    version: AtomicU32, // Odd while a store is in progress
+}
+
+impl AtomicParticles {
+    // Copy a set of particles in the xy plane into atomic storage
+    pub fn from_particles(particles: &[Particle]) -> Self {
+        Self::new(particles, 2)
+    }
+
+    // Copy a set of particles into atomic storage for a 2D or 3D system
+    pub fn new(particles: &[Particle], dimensions: usize) -> Self {
+        let positions = particles
+            .iter()
+            .map(|particle| AtomicU64::new(pack(particle)))
+            .collect();
+        let depths = (dimensions > 2).then(|| {
+            particles
+                .iter()
+                .map(|particle| Depth {
+                    z: AtomicU32::new(particle.position()[2].to_bits()),
+                    version: AtomicU32::new(0),
+                })
+                .collect()
+        });
+        AtomicParticles {
+            particles: particles.to_vec(),
+            positions,
+            depths,
+            removed: particles.iter().map(|_| AtomicBool::new(false)).collect(),
+        }
+    }
+
+    // Get the number of particles
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    // Check whether there are no particles
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // Read one particle at its latest stored position
+    pub fn load(&self, index: usize) -> Particle {
+        let mut particle = self.particles[index];
+        let (xy, z) = match &self.depths {
+            Some(depths) => {
+                let depth = &depths[index];
+                loop {
+                    let before = depth.version.load(Ordering::Acquire);
+                    if before % 2 == 1 {
+                        hint::spin_loop();
+                        continue;
+                    }
+                    let xy = self.positions[index].load(Ordering::Relaxed);
+                    let z = depth.z.load(Ordering::Relaxed);
+                    fence(Ordering::Acquire);
+                    if depth.version.load(Ordering::Relaxed) == before {
+                        break (xy, f32::from_bits(z));
+                    }
+                }
+            }
+            None => (self.positions[index].load(Ordering::Relaxed), 0.0),
+        };
+        let (x, y) = unpack(xy);
+        particle.set_position([x, y, z]);
+        particle
+    }
+
+    // Overwrite one particle's position
+    pub fn store(&self, index: usize, particle: &Particle) {
+        match &self.depths {
+            Some(depths) => {
+                let depth = &depths[index];
+                let version = depth.version.load(Ordering::Relaxed);
+                depth
+                    .version
+                    .store(version.wrapping_add(1), Ordering::Relaxed);
+                fence(Ordering::Release);
+                self.positions[index].store(pack(particle), Ordering::Relaxed);
+                depth
+                    .z
+                    .store(particle.position()[2].to_bits(), Ordering::Relaxed);
+                depth
+                    .version
+                    .store(version.wrapping_add(2), Ordering::Release);
+            }
+            None => self.positions[index].store(pack(particle), Ordering::Relaxed),
+        }
+    }
+
+    // Take one particle out of the system, e.g. when it crosses an absorbing
+    // wall. It can still be loaded by index but is left out of snapshots.
+    pub fn remove(&self, index: usize) {
+        self.removed[index].store(true, Ordering::Relaxed);
+    }
+
+    // Read every particle still in the system, one at a time. Not a consistent
+    // snapshot while other threads are storing or removing; see the type's comment.
+    pub fn snapshot(&self) -> Vec<Particle> {
+        (0..self.len())
+            .filter(|&i| !self.removed[i].load(Ordering::Relaxed))
+            .map(|i| self.load(i))
+            .collect()
+    }
+}
+
+fn pack(particle: &Particle) -> u64 {
+    let (x, y) = particle.get_position();
+    (x.to_bits() as u64) | ((y.to_bits() as u64) << 32)
+}
+
+fn unpack(bits: u64) -> (f32, f32) {
+    (
+        f32::from_bits(bits as u32),
+        f32::from_bits((bits >> 32) as u32),
+    )
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+
+// What happens to a particle that moves past the walls of the enclosure
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    Clamp,     // Stop at the wall; the particle loses its velocity into the wall
+    Reflect,   // Bounce off the wall, reversing the velocity into it
+    Periodic,  // Leave through one wall and come back through the opposite one
+    Absorbing, // Remove the particle from the system
+}
+
+impl Boundary {
+    pub const NAMES: &'static [&'static str] = &["clamp", "reflect", "periodic", "absorbing"];
+
+    // Get the shortest signed distance from `from` to `to` along one axis.
+    // With periodic walls this may be the way round through the wall (the minimum image).
+    pub fn offset(&self, from: f32, to: f32, size: f32) -> f32 {
+        let offset = to - from;
+        match self {
+            Boundary::Periodic => offset - size * (offset / size).round(),
+            _ => offset,
+        }
+    }
+
+    // Bring a coordinate back into [0, size) with periodic walls; other walls
+    // leave it alone
+    pub fn wrap(&self, position: f32, size: f32) -> f32 {
+        match self {
+            Boundary::Periodic => {
+                let wrapped = position.rem_euclid(size);
+                // Rounding can give exactly `size` for a tiny negative position
+                if wrapped < size {
+                    wrapped
+                } else {
+                    0.0
+                }
+            }
+            _ => position,
+        }
+    }
+}
+
+impl FromStr for Boundary {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "clamp" => Ok(Boundary::Clamp),
+            "reflect" => Ok(Boundary::Reflect),
+            "periodic" => Ok(Boundary::Periodic),
+            "absorbing" => Ok(Boundary::Absorbing),
+            _ => Err(ConfigError::InvalidValue {
+                key: "--boundary".to_string(),
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Boundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Boundary::Clamp => write!(f, "clamp"),
+            Boundary::Reflect => write!(f, "reflect"),
+            Boundary::Periodic => write!(f, "periodic"),
+            Boundary::Absorbing => write!(f, "absorbing"),
+        }
+    }
+}
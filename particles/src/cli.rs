@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+use crate::{
+    Boundary, CollisionMethod, ConfigError, EventFormat, Layout, MovementModel, RadiusDistribution,
+    RunOptions, RwFairness, SimulationConfig, Snapshot, Strategy, TrajectoryFormat,
+};
+
+// Command-line options shared by the simulator binaries
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub config: SimulationConfig,
+    pub run: RunOptions,
+    pub verbosity: u8, // 0 = total only, 1 = normal, 2+ = also print positions
+    pub events: Option<PathBuf>, // Collision event log, .csv or .jsonl
+    pub population: Option<PathBuf>, // Particles of each species after every step, as CSV
+    pub trajectory: Option<PathBuf>, // Particle positions every few steps, .csv, .xyz or .bin
+    pub trajectory_every: u64, // Steps between trajectory frames
+    pub resume: Option<Snapshot>, // Carry on from this snapshot instead of starting afresh
+    pub checkpoint: Option<PathBuf>, // Save a snapshot here at the end of the run
+    pub checkpoint_every: Option<u64>, // Also save it every this many steps
+    pub runs: usize, // Repeat the run this many times with successive seeds and summarise the rates
+    pub help: bool,
+}
+
+impl Cli {
+    // Parse the arguments after the program name.
+    // `default_strategy` is used when `--strategy` is not given.
+    pub fn parse<I>(args: I, default_strategy: Strategy) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut cli = Cli {
+            config: SimulationConfig::default(),
+            run: RunOptions::new(default_strategy),
+            verbosity: 1,
+            events: None,
+            population: None,
+            trajectory: None,
+            trajectory_every: 1,
+            resume: None,
+            checkpoint: None,
+            checkpoint_every: None,
+            runs: 1,
+            help: false,
+        };
+        let mut overrides = Vec::new();
+        let mut move_threads_set = false; // Given by --move-threads rather than --threads
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Flags that take no value
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    cli.help = true;
+                    continue;
+                }
+                "-q" | "--quiet" => {
+                    cli.verbosity = 0;
+                    continue;
+                }
+                "-v" | "--verbose" => {
+                    cli.verbosity = cli.verbosity.max(1) + 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let (key, inline) = match arg.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if !key.starts_with("--") {
+                return Err(ConfigError::UnknownKey(key));
+            }
+            let value = match inline.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(ConfigError::MissingValue(key)),
+            };
+            match key.as_str() {
+                "--config" => cli.config = SimulationConfig::from_file(&value)?,
+                "--strategy" => cli.run.strategy = value.parse()?,
+                "--threads" => {
+                    let threads = parse_threads(&key, &value)?;
+                    cli.run.move_threads = threads;
+                    cli.run.collision_threads = threads;
+                    move_threads_set = false;
+                }
+                "--fairness" => cli.run.fairness = value.parse()?,
+                "--events" => {
+                    if EventFormat::from_path(&value).is_none() {
+                        return Err(ConfigError::InvalidValue { key, value });
+                    }
+                    cli.events = Some(PathBuf::from(value));
+                }
+                // The snapshot's settings replace any loaded so far; later options override them
+                "--resume" => {
+                    let snapshot = Snapshot::load(&value)
+                        .map_err(|err| ConfigError::Invalid(err.to_string()))?;
+                    cli.config = snapshot.config.clone();
+                    cli.resume = Some(snapshot);
+                }
+                "--checkpoint" => cli.checkpoint = Some(PathBuf::from(value)),
+                "--checkpoint-every" => {
+                    cli.checkpoint_every = Some(parse_threads(&key, &value)? as u64)
+                }
+                "--trajectory" => {
+                    if TrajectoryFormat::from_path(&value).is_none() {
+                        return Err(ConfigError::InvalidValue { key, value });
+                    }
+                    cli.trajectory = Some(PathBuf::from(value));
+                }
+                "--trajectory-every" => cli.trajectory_every = parse_threads(&key, &value)? as u64,
+                "--runs" => cli.runs = parse_threads(&key, &value)?,
+                "--population" => cli.population = Some(PathBuf::from(value)),
+                "--move-threads" => {
+                    cli.run.move_threads = parse_threads(&key, &value)?;
+                    move_threads_set = true;
+                }
+                "--collision-threads" => cli.run.collision_threads = parse_threads(&key, &value)?,
+                // Anything else is a simulation setting, applied after any config file
+                _ => overrides.push((key, value)),
+            }
+        }
+
+        for (key, value) in overrides {
+            cli.config.set(&key, &value)?;
+        }
+        // --threads only sets the collision threads of a strategy with one mover
+        if !move_threads_set && cli.run.strategy.has_one_mover() {
+            cli.run.move_threads = 1;
+        }
+        cli.config.validate()?;
+        cli.run.check(&cli.config)?;
+        if cli.trajectory.is_some() && !cli.run.records_each_step() {
+            return Err(ConfigError::Invalid(format!(
+                "--trajectory needs single-lock, split, double-buffer or rwlock with \
+                 alternate fairness, not {} with {} fairness",
+                cli.run.strategy, cli.run.fairness
+            )));
+        }
+        if cli.checkpoint_every.is_some() && cli.checkpoint.is_none() {
+            return Err(ConfigError::Invalid(
+                "--checkpoint-every needs --checkpoint".to_string(),
+            ));
+        }
+        if cli.runs > 1
+            && (cli.events.is_some()
+                || cli.trajectory.is_some()
+                || cli.population.is_some()
+                || cli.checkpoint.is_some()
+                || cli.resume.is_some())
+        {
+            return Err(ConfigError::Invalid(
+                "--runs cannot be combined with --events, --trajectory, --population, \
+                 --checkpoint or --resume"
+                    .to_string(),
+            ));
+        }
+        Ok(cli)
+    }
+
+    // Usage text for `--help`
+    pub fn usage(program: &str) -> String {
+        format!(
+            "Usage: {} [OPTIONS]
+
+Simulation:
+    --config FILE              Load settings from a .toml or .json file
+    --particles N              Number of particles
+    --steps N                  Number of steps to run (default 1000)
+    --duration SECS            Run for SECS seconds of wall-clock time instead of a step count
+    --dimensions N             2 for a square enclosure, 3 for a cube (default 2)
+    --enclosure-size SIZE      Side length of the enclosure
+    --threshold DIST           Collide within DIST of each other; same as --radius DIST/2
+    --collision-method NAME    Collision broad phase: {}
+    --collision-workers N      Worker threads sharing each collision check
+    --move-workers N           Worker threads sharing each move phase
+    --layout FORM              Starting positions: {}
+                               A file is a .csv with x, y (and z) columns, one row per particle
+    --seed N                   Seed the random number generator for a repeatable run
+
+Physics:
+    --movement NAME            Movement model: {}
+    --boundary NAME            What the enclosure walls do: {}
+    --dt SECS                  Simulated time per step (default 1)
+    --diffusion D              Diffusion coefficient for brownian motion
+    --speed V                  Starting speed of ballistic particles
+    --mass M                   Mass of every particle
+    --radius DIST              Particle radii, {}; overlapping particles collide
+    --restitution E            Resolve collisions, bouncing particles apart (1 = elastic)
+                               Mixtures of species and their reactions are set in a config file
+
+Threading:
+    --strategy NAME            Concurrency strategy: {}
+    --threads N                Set both move and collision thread counts; only collision
+                               threads for double-buffer and rwlock, which have one mover
+    --move-threads N           Threads moving particles
+    --collision-threads N      Threads checking for collisions (readers for rwlock)
+    --fairness NAME            Lock sharing for the rwlock strategy: {}
+
+Output:
+    -q, --quiet                Only print the collision total
+    -v, --verbose              Also print particle positions
+    --runs N                   Repeat the run N times with successive seeds and summarise the
+                               collision rates, comparing them with theory
+    --events FILE              Log every collision to a .csv or .jsonl file
+    --trajectory FILE          Record positions to a .csv, .xyz or .bin file; add .gz to compress
+    --trajectory-every N       Record every N steps (default 1)
+    --population FILE          Write the particles of each species after every step to a .csv file
+    --checkpoint FILE          Save the whole simulation to FILE at the end of the run
+    --checkpoint-every N       Also save it every N steps
+    --resume FILE              Carry on from a saved simulation, until --steps steps in all
+    -h, --help                 Show this help
+",
+            program,
+            CollisionMethod::NAMES.join(", "),
+            Layout::FORMS.join(", "),
+            MovementModel::NAMES.join(", "),
+            Boundary::NAMES.join(", "),
+            RadiusDistribution::FORMS.join(", "),
+            Strategy::NAMES.join(", "),
+            RwFairness::NAMES.join(", ")
+        )
+    }
+}
+
+// Thread counts and intervals must be at least one
+fn parse_threads(key: &str, value: &str) -> Result<usize, ConfigError> {
+    match value.parse() {
+        Ok(threads) if threads > 0 => Ok(threads),
+        _ => Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Boundary, CollisionMethod, Interaction, InteractionRule, Layout, MovementModel,
+    RadiusDistribution, Reaction, Species, DIFFUSION, DT, ENCLOSURE_SIZE, INITIAL_SPEED,
+    NUM_OF_PARTICLES, NUM_OF_STEPS, PARTICLE_MASS, PARTICLE_RADIUS,
+};
+
+// The name of the single species of a config that lists none
+pub const DEFAULT_SPECIES: &str = "default";
+
+// Runtime settings for a simulation, defaulting to the original constants
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub num_of_particles: usize, // Ignored when species are listed
+    pub dimensions: usize,       // 2 for a square enclosure, 3 for a cube
+    pub enclosure_size: f32,     // Side length of the enclosure
+    pub steps: u64,              // Number of steps (ticks) to run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_duration: Option<u64>, // Run for this many seconds instead of a step count
+    pub collision_method: CollisionMethod, // Broad phase used to find colliding pairs
+    pub collision_workers: usize, // Threads sharing each collision check
+    pub move_workers: usize,     // Threads sharing each move phase
+    pub movement: MovementModel, // How particles move between collision checks
+    pub boundary: Boundary,      // What the walls of the enclosure do
+    pub dt: f32,                 // Simulated time per step
+    pub diffusion: f32,          // Diffusion coefficient for Brownian motion
+    pub initial_speed: f32,      // Starting speed of ballistic particles, in a random direction
+    pub particle_mass: f32,
+    pub radius: RadiusDistribution, // Radii of new particles; touching particles collide
+    pub layout: Layout,             // Where new particles start
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub species: Vec<Species>, // A mixture; if empty, one species from the settings above
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub interactions: Vec<InteractionRule>, // Species pairs that do not simply collide
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restitution: Option<f32>, // Resolve collisions with this restitution, 1 = elastic
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>, // Seed for the random number generator, random if unset
+}
+
+// Errors raised while loading, overriding or validating a configuration
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, std::io::Error),
+    Parse(String),
+    UnknownFormat(String),
+    UnknownKey(String),
+    MissingValue(String),
+    InvalidValue { key: String, value: String },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "cannot read {}: {}", path, err),
+            ConfigError::Parse(msg) => write!(f, "cannot parse config: {}", msg),
+            ConfigError::UnknownFormat(path) => {
+                write!(
+                    f,
+                    "unknown config format for {} (expected .toml or .json)",
+                    path
+                )
+            }
+            ConfigError::UnknownKey(key) => write!(f, "unknown option '{}'", key),
+            ConfigError::MissingValue(key) => write!(f, "option '{}' needs a value", key),
+            ConfigError::InvalidValue { key, value } => {
+                write!(f, "invalid value '{}' for '{}'", value, key)
+            }
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            num_of_particles: NUM_OF_PARTICLES,
+            dimensions: 2,
+            enclosure_size: ENCLOSURE_SIZE,
+            steps: NUM_OF_STEPS,
+            move_duration: None,
+            collision_method: CollisionMethod::Grid,
+            collision_workers: 1,
+            move_workers: 1,
+            movement: MovementModel::RandomWalk,
+            boundary: Boundary::Clamp,
+            dt: DT,
+            diffusion: DIFFUSION,
+            initial_speed: INITIAL_SPEED,
+            particle_mass: PARTICLE_MASS,
+            radius: RadiusDistribution::Fixed(PARTICLE_RADIUS),
+            layout: Layout::Uniform,
+            species: Vec::new(),
+            interactions: Vec::new(),
+            restitution: None,
+            seed: None,
+        }
+    }
+}
+
+impl SimulationConfig {
+    // Load a config file, picking the format from the file extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|err| ConfigError::Io(name.clone(), err))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&text),
+            Some("json") => Self::from_json_str(&text),
+            _ => Err(ConfigError::UnknownFormat(name)),
+        }
+    }
+
+    // Parse a config from TOML text; missing fields keep their defaults
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: SimulationConfig =
+            toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Parse a config from JSON text; missing fields keep their defaults
+    pub fn from_json_str(text: &str) -> Result<Self, ConfigError> {
+        let config: SimulationConfig =
+            serde_json::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Override a single setting by name; accepts `--name` or `name`
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        match key.trim_start_matches("--").replace('-', "_").as_str() {
+            "num_of_particles" | "particles" => {
+                self.num_of_particles = value.parse().map_err(|_| invalid())?
+            }
+            "dimensions" => self.dimensions = value.parse().map_err(|_| invalid())?,
+            "enclosure_size" => self.enclosure_size = value.parse().map_err(|_| invalid())?,
+            "move_duration" | "duration" => {
+                self.move_duration = Some(value.parse().map_err(|_| invalid())?)
+            }
+            "steps" => self.steps = value.parse().map_err(|_| invalid())?,
+            // The original setting: point-like particles colliding within a distance
+            "collision_threshold" | "threshold" => {
+                let threshold: f32 = value.parse().map_err(|_| invalid())?;
+                self.radius = RadiusDistribution::Fixed(threshold / 2.0)
+            }
+            "collision_method" => self.collision_method = value.parse()?,
+            "collision_workers" => self.collision_workers = value.parse().map_err(|_| invalid())?,
+            "move_workers" => self.move_workers = value.parse().map_err(|_| invalid())?,
+            "movement" => self.movement = value.parse()?,
+            "boundary" => self.boundary = value.parse()?,
+            "dt" => self.dt = value.parse().map_err(|_| invalid())?,
+            "diffusion" => self.diffusion = value.parse().map_err(|_| invalid())?,
+            "initial_speed" | "speed" => {
+                self.initial_speed = value.parse().map_err(|_| invalid())?
+            }
+            "particle_mass" | "mass" => {
+                self.particle_mass = value.parse().map_err(|_| invalid())?
+            }
+            "radius" => self.radius = value.parse()?,
+            "layout" => self.layout = value.parse()?,
+            "restitution" => self.restitution = Some(value.parse().map_err(|_| invalid())?),
+            "seed" => self.seed = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+
+    // Reject settings the simulation cannot run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.dimensions != 2 && self.dimensions != 3 {
+            return Err(ConfigError::Invalid(format!(
+                "dimensions must be 2 or 3, got {}",
+                self.dimensions
+            )));
+        }
+        if !self.enclosure_size.is_finite() || self.enclosure_size <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "enclosure_size must be positive, got {}",
+                self.enclosure_size
+            )));
+        }
+        self.radius.validate()?;
+        self.layout.validate()?;
+        if !self.dt.is_finite() || self.dt <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "dt must be positive, got {}",
+                self.dt
+            )));
+        }
+        if !self.particle_mass.is_finite() || self.particle_mass <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "particle_mass must be positive, got {}",
+                self.particle_mass
+            )));
+        }
+        for (name, value) in [
+            ("diffusion", self.diffusion),
+            ("initial_speed", self.initial_speed),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(ConfigError::Invalid(format!(
+                    "{} must not be negative, got {}",
+                    name, value
+                )));
+            }
+        }
+        let species = self.species_list();
+        for (i, listed) in self.species.iter().enumerate() {
+            listed.validate()?;
+            if species[..i].iter().any(|other| other.name == listed.name) {
+                return Err(ConfigError::Invalid(format!(
+                    "species '{}' is listed twice",
+                    listed.name
+                )));
+            }
+        }
+        for rule in &self.interactions {
+            for name in &rule.between {
+                if !species.iter().any(|species| &species.name == name) {
+                    return Err(ConfigError::Invalid(format!(
+                        "interaction between unknown species '{}'",
+                        name
+                    )));
+                }
+            }
+            let [a, b] = &rule.between;
+            match (rule.interaction, &rule.reaction) {
+                (Interaction::React, None) => {
+                    return Err(ConfigError::Invalid(format!(
+                        "reacting pair {} + {} needs a reaction",
+                        a, b
+                    )));
+                }
+                (Interaction::React, Some(reaction)) => {
+                    if let Some(product) = reaction.product() {
+                        if !species.iter().any(|species| species.name == product) {
+                            return Err(ConfigError::Invalid(format!(
+                                "reaction of {} + {} makes unknown species '{}'",
+                                a, b, product
+                            )));
+                        }
+                    }
+                }
+                (_, Some(_)) => {
+                    return Err(ConfigError::Invalid(format!(
+                        "pair {} + {} has a reaction but does not react",
+                        a, b
+                    )));
+                }
+                (_, None) => {}
+            }
+        }
+        if let Some(restitution) = self.restitution {
+            if !(0.0..=1.0).contains(&restitution) {
+                return Err(ConfigError::Invalid(format!(
+                    "restitution must be between 0 and 1, got {}",
+                    restitution
+                )));
+            }
+        }
+        if self.collision_workers == 0 || self.move_workers == 0 {
+            return Err(ConfigError::Invalid(
+                "collision_workers and move_workers must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Get the species to create, in order. A config without a species list
+    // has a single species made from the top-level particle settings.
+    pub fn species_list(&self) -> Vec<Species> {
+        if !self.species.is_empty() {
+            return self.species.clone();
+        }
+        vec![Species {
+            name: DEFAULT_SPECIES.to_string(),
+            count: self.num_of_particles,
+            mass: self.particle_mass,
+            radius: self.radius,
+            diffusion: self.diffusion,
+            initial_speed: self.initial_speed,
+        }]
+    }
+
+    // Get the name of a species by its index in the species list
+    pub fn species_name(&self, species: usize) -> &str {
+        match self.species.get(species) {
+            Some(species) => &species.name,
+            None => DEFAULT_SPECIES,
+        }
+    }
+
+    // Get the diffusion coefficient of a species
+    pub fn diffusion_of(&self, species: usize) -> f32 {
+        self.species
+            .get(species)
+            .map_or(self.diffusion, |species| species.diffusion)
+    }
+
+    // Get the index of a species in the species list by name
+    pub fn species_index(&self, name: &str) -> Option<usize> {
+        if self.species.is_empty() {
+            return (name == DEFAULT_SPECIES).then_some(0);
+        }
+        self.species.iter().position(|species| species.name == name)
+    }
+
+    // Get what happens when particles of two species touch
+    pub fn interaction(&self, first: usize, second: usize) -> Interaction {
+        self.rule_for(first, second)
+            .map_or(Interaction::Count, |rule| rule.interaction)
+    }
+
+    // Get the reaction fired when particles of two species touch, if any
+    pub fn reaction(&self, first: usize, second: usize) -> Option<&Reaction> {
+        self.rule_for(first, second)?.reaction.as_ref()
+    }
+
+    // Check whether any pair of species reacts
+    pub fn has_reactions(&self) -> bool {
+        self.interactions
+            .iter()
+            .any(|rule| rule.interaction == Interaction::React)
+    }
+
+    // Find the rule for a pair of species. The last rule covering the pair wins.
+    fn rule_for(&self, first: usize, second: usize) -> Option<&InteractionRule> {
+        if self.interactions.is_empty() {
+            return None;
+        }
+        let (first, second) = (self.species_name(first), self.species_name(second));
+        self.interactions
+            .iter()
+            .rev()
+            .find(|rule| rule.covers(first, second))
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{
+    analyse_rates, expected_contact_probability, run, run_with_checkpoints, Cli, CollisionRates,
+    EventWriter, ParticleSystem, Strategy, TrajectoryWriter,
+};
+
+// Print every particle position under a heading
+fn print_positions(heading: &str, system: &ParticleSystem) {
+    println!("{}", heading);
+    for particle in system.particles() {
+        let (id, [x, y, z]) = (particle.id(), particle.position());
+        match system.config().dimensions {
+            2 => println!("Particle {}: ({}, {})", id, x, y),
+            _ => println!("Particle {}: ({}, {}, {})", id, x, y, z),
+        }
+    }
+}
+
+// Why a simulator run stopped early: its exit code and what to print
+struct Failure(u8, String);
+
+impl Failure {
+    // The command line or the settings it gives are wrong
+    fn settings(err: impl fmt::Display) -> Self {
+        Failure(2, err.to_string())
+    }
+
+    // An output file could not be written
+    fn output(path: &Path, err: impl fmt::Display) -> Self {
+        Failure(1, format!("cannot write {}: {}", path.display(), err))
+    }
+}
+
+// Run a simulator binary: parse the arguments after its name, run the
+// simulation and report on it. `default_strategy` is used when `--strategy` is
+// not given. Returns 2 for a bad command line and 1 for a failed write.
+pub fn run_simulator<I>(program: &str, args: I, default_strategy: Strategy) -> ExitCode
+where
+    I: IntoIterator<Item = String>,
+{
+    // Read the simulation settings from the command line
+    let cli = match Cli::parse(args, default_strategy) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("error: {}\n\nRun with --help for usage.", err);
+            return ExitCode::from(2);
+        }
+    };
+    if cli.help {
+        print!("{}", Cli::usage(program));
+        return ExitCode::SUCCESS;
+    }
+    match simulate(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure(code, message)) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(code)
+        }
+    }
+}
+
+fn simulate(cli: &Cli) -> Result<(), Failure> {
+    // Summarise the collision rates of repeated runs instead of making one
+    if cli.runs > 1 {
+        let analysis = analyse_rates(&cli.config, &cli.run, cli.runs).map_err(Failure::settings)?;
+        if cli.verbosity == 0 {
+            for run in &analysis.runs {
+                println!("{}", run.collisions);
+            }
+        } else {
+            println!("{}", analysis);
+        }
+        return Ok(());
+    }
+
+    // Initialize the particle system, or pick up a saved one
+    let mut system = match cli.resume.clone() {
+        Some(mut snapshot) => {
+            snapshot.config = cli.config.clone();
+            ParticleSystem::from_snapshot(snapshot).map_err(Failure::settings)?
+        }
+        None => ParticleSystem::try_new(cli.config.clone()).map_err(Failure::settings)?,
+    };
+    if cli.population.is_some() {
+        system.keep_population();
+    }
+    let system = Arc::new(Mutex::new(system));
+
+    if cli.verbosity >= 1 {
+        println!("Seed: {}", system.lock().unwrap().seed());
+    }
+
+    // Print initial positions
+    if cli.verbosity >= 2 {
+        print_positions("Initial positions:", &system.lock().unwrap());
+    }
+
+    // Write collision events to the log on a background thread
+    let event_log = match &cli.events {
+        Some(path) => {
+            let mut writer = EventWriter::create(path).map_err(|err| Failure::output(path, err))?;
+            let events = system.lock().unwrap().collision_events();
+            Some(thread::spawn(move || {
+                for event in events {
+                    writer.write(&event)?;
+                }
+                writer.flush()
+            }))
+        }
+        None => None,
+    };
+
+    // Write trajectory frames out on another background thread
+    let trajectory = match &cli.trajectory {
+        Some(path) => {
+            let config = system.lock().unwrap().config().clone();
+            let mut writer = TrajectoryWriter::create(path, &config)
+                .map_err(|err| Failure::output(path, err))?;
+            let frames = system.lock().unwrap().frames(cli.trajectory_every);
+            Some(thread::spawn(move || {
+                for frame in frames {
+                    writer.write(&frame)?;
+                }
+                writer.into_inner()?.close()
+            }))
+        }
+        None => None,
+    };
+
+    // Run the simulation with the requested strategy
+    let report = match &cli.checkpoint {
+        Some(path) => run_with_checkpoints(&system, &cli.run, path, cli.checkpoint_every)
+            .map_err(|err| Failure::output(path, err))?,
+        None => run(&system, &cli.run),
+    };
+    let total = report.collisions;
+
+    // Close the event channel and wait for the log to be written out
+    if let Some(event_log) = event_log {
+        system.lock().unwrap().stop_collision_events();
+        let path = cli.events.as_deref().unwrap();
+        event_log
+            .join()
+            .unwrap()
+            .map_err(|err| Failure::output(path, err))?;
+    }
+
+    // Likewise for the trajectory
+    if let Some(trajectory) = trajectory {
+        system.lock().unwrap().stop_frames();
+        let path = cli.trajectory.as_deref().unwrap();
+        trajectory
+            .join()
+            .unwrap()
+            .map_err(|err| Failure::output(path, err))?;
+    }
+
+    // Write out the population time series
+    if let Some(path) = &cli.population {
+        system
+            .lock()
+            .unwrap()
+            .population()
+            .save(path)
+            .map_err(|err| Failure::output(path, err))?;
+    }
+
+    // Print collision count
+    if cli.verbosity == 0 {
+        println!("{}", total);
+    } else {
+        println!("\nSteps: {}", system.lock().unwrap().step_count());
+        println!("Total collisions: {}", total);
+        println!("Overlap frames: {}", report.overlaps);
+        let rates = CollisionRates::measure(&system.lock().unwrap());
+        println!("Collisions per step: {:.6}", rates.per_step());
+        println!("Collisions per particle: {:.6}", rates.per_particle());
+        match expected_contact_probability(&cli.config) {
+            Some(expected) => println!(
+                "Contact probability: {:.6} (expected {:.6})",
+                rates.contact_probability(),
+                expected
+            ),
+            None => println!("Contact probability: {:.6}", rates.contact_probability()),
+        }
+        if system.lock().unwrap().config().species.len() > 1 {
+            for ((a, b), count) in &report.species_collisions {
+                println!("  {} + {}: {}", a, b, count);
+            }
+        }
+        println!("Particles: {}", system.lock().unwrap().get_particle_count());
+        if report.population.len() > 1 {
+            for (species, count) in &report.population {
+                println!("  {}: {}", species, count);
+            }
+        }
+        if report.writer_acquisitions > 0 {
+            println!(
+                "Writer wait: {:?} over {} acquisitions",
+                report.writer_wait, report.writer_acquisitions
+            );
+        }
+        if report.reader_acquisitions > 0 {
+            println!(
+                "Reader wait: {:?} over {} acquisitions",
+                report.reader_wait, report.reader_acquisitions
+            );
+        }
+    }
+
+    // Print updated positions
+    if cli.verbosity >= 2 {
+        print_positions(
+            "\nUpdated positions after simulation:",
+            &system.lock().unwrap(),
+        );
+    }
+    Ok(())
+}
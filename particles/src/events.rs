@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Particle, SimulationConfig};
+
+// Two particles found in contact during a collision check
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Contact {
+    pub a: u64,                  // Smaller particle id
+    pub b: u64,                  // Larger particle id
+    pub species: (usize, usize), // Species of a and b
+    pub distance: f32,
+    pub midpoint: [f32; 3], // z is zero in a 2D system
+}
+
+impl Contact {
+    // Describe the contact between two particles. With periodic walls the
+    // distance and midpoint are taken the shortest way round.
+    pub fn between(first: &Particle, second: &Particle, config: &SimulationConfig) -> Self {
+        let (first, second) = if first.id() <= second.id() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let position = first.position();
+        let offset = first.offset_to(second, config);
+        let mut midpoint = [0.0; 3];
+        for (axis, value) in midpoint.iter_mut().enumerate() {
+            *value = config
+                .boundary
+                .wrap(position[axis] + offset[axis] / 2.0, config.enclosure_size);
+        }
+        Contact {
+            a: first.id(),
+            b: second.id(),
+            species: (first.species(), second.species()),
+            distance: offset.iter().map(|axis| axis * axis).sum::<f32>().sqrt(),
+            midpoint,
+        }
+    }
+}
+
+// A pair of particles coming into contact
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollisionEvent {
+    pub step: u64, // The step in which the pair came into contact, counting from 1
+    pub a: u64,    // Smaller particle id
+    pub b: u64,    // Larger particle id
+    pub distance: f32,
+    pub midpoint: [f32; 3], // z is zero in a 2D system
+}
+
+impl CollisionEvent {
+    pub fn new(step: u64, contact: &Contact) -> Self {
+        CollisionEvent {
+            step,
+            a: contact.a,
+            b: contact.b,
+            distance: contact.distance,
+            midpoint: contact.midpoint,
+        }
+    }
+}
+
+// File formats for a collision event log
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventFormat {
+    Csv,
+    JsonLines,
+}
+
+impl EventFormat {
+    // Pick the format from a file extension: .csv, or .jsonl / .ndjson
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Some(EventFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Some(EventFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+// Writes collision events one per line
+pub struct EventWriter<W: Write> {
+    out: W,
+    format: EventFormat,
+}
+
+impl EventWriter<BufWriter<File>> {
+    // Create a log file, picking the format from its extension
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let format = EventFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "event log must end in .csv or .jsonl",
+            )
+        })?;
+        EventWriter::new(BufWriter::new(File::create(path)?), format)
+    }
+}
+
+impl<W: Write> EventWriter<W> {
+    // Start a log in the given format; CSV logs begin with a header row
+    pub fn new(mut out: W, format: EventFormat) -> io::Result<Self> {
+        if format == EventFormat::Csv {
+            writeln!(out, "step,a,b,distance,midpoint_x,midpoint_y,midpoint_z")?;
+        }
+        Ok(EventWriter { out, format })
+    }
+
+    // Append one event
+    pub fn write(&mut self, event: &CollisionEvent) -> io::Result<()> {
+        match self.format {
+            EventFormat::Csv => writeln!(
+                self.out,
+                "{},{},{},{},{},{},{}",
+                event.step,
+                event.a,
+                event.b,
+                event.distance,
+                event.midpoint[0],
+                event.midpoint[1],
+                event.midpoint[2]
+            ),
+            EventFormat::JsonLines => {
+                serde_json::to_writer(&mut self.out, event)?;
+                writeln!(self.out)
+            }
+        }
+    }
+
+    // Flush anything buffered to the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
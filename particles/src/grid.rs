@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ConfigError, Particle};
+
+// How ParticleSystem::check_collisions finds the pairs to test
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionMethod {
+    BruteForce, // Test every pair, O(n^2); kept as the reference
+    Grid,       // Only test pairs in neighbouring cells of a spatial hash
+}
+
+impl CollisionMethod {
+    pub const NAMES: &'static [&'static str] = &["brute-force", "grid"];
+}
+
+impl FromStr for CollisionMethod {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "brute-force" => Ok(CollisionMethod::BruteForce),
+            "grid" => Ok(CollisionMethod::Grid),
+            _ => Err(ConfigError::InvalidValue {
+                key: "--collision-method".to_string(),
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for CollisionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollisionMethod::BruteForce => write!(f, "brute-force"),
+            CollisionMethod::Grid => write!(f, "grid"),
+        }
+    }
+}
+
+// Cells to compare against besides a cell itself. Only half of the neighbours
+// are listed, those after the cell in (x, y, z) order, so that every pair of
+// cells is visited once.
+const FORWARD_NEIGHBOURS_2D: [Cell; 4] = [[1, -1, 0], [1, 0, 0], [1, 1, 0], [0, 1, 0]];
+const FORWARD_NEIGHBOURS_3D: [Cell; 13] = [
+    [1, -1, -1],
+    [1, -1, 0],
+    [1, -1, 1],
+    [1, 0, -1],
+    [1, 0, 0],
+    [1, 0, 1],
+    [1, 1, -1],
+    [1, 1, 0],
+    [1, 1, 1],
+    [0, 1, -1],
+    [0, 1, 0],
+    [0, 1, 1],
+    [0, 0, 1],
+];
+
+// A grid cell, by its index along x, y and z; z is always zero in 2D
+pub type Cell = [i64; 3];
+
+// Uniform-grid broad phase: particles are bucketed into square (or cubic)
+// cells so that only particles in the same or neighbouring cells can collide
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<usize>>, // Particle indices in each occupied cell
+    neighbours: &'static [Cell],      // Forward neighbours for the number of dimensions
+    flat: bool,                       // 2D: every particle is put in the z = 0 layer of cells
+    wrap: Option<i64>,                // Cells per side when the grid wraps round periodic walls
+}
+
+impl SpatialGrid {
+    // Bucket the particles into cells of roughly `reach` across, the largest
+    // centre distance at which two particles can collide.
+    // The cells are made a hair wider than that so that rounding in
+    // Particle::collide can never accept a pair two cells apart.
+    pub fn new(particles: &[Particle], reach: f32, dimensions: usize) -> Self {
+        Self::bucket(particles, reach * 1.001, dimensions, None)
+    }
+
+    // Bucket particles in an enclosure with periodic walls. The enclosure is
+    // cut into a whole number of cells at least `reach` across, and the cells
+    // along each wall neighbour the ones along the opposite wall. Returns None
+    // if there would be fewer than three cells a side, as a cell would then
+    // neighbour another on both sides and pairs would be visited twice.
+    pub fn periodic(
+        particles: &[Particle],
+        reach: f32,
+        size: f32,
+        dimensions: usize,
+    ) -> Option<Self> {
+        let per_side = (size / (reach * 1.001)).floor() as i64;
+        if per_side < 3 {
+            return None;
+        }
+        Some(Self::bucket(
+            particles,
+            size / per_side as f32,
+            dimensions,
+            Some(per_side),
+        ))
+    }
+
+    fn bucket(
+        particles: &[Particle],
+        cell_size: f32,
+        dimensions: usize,
+        wrap: Option<i64>,
+    ) -> Self {
+        let neighbours: &'static [Cell] = match dimensions {
+            2 => &FORWARD_NEIGHBOURS_2D,
+            _ => &FORWARD_NEIGHBOURS_3D,
+        };
+        let mut grid = SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            neighbours,
+            flat: dimensions == 2,
+            wrap,
+        };
+        for (i, particle) in particles.iter().enumerate() {
+            let cell = grid.cell_of(particle);
+            grid.cells.entry(cell).or_default().push(i);
+        }
+        grid
+    }
+
+    // Get the cell a particle falls into. A 2D grid ignores z, which its
+    // neighbours never look along, so a particle off the plane (say from a
+    // 3D trajectory) is still tested against everything near it in x and y.
+    fn cell_of(&self, particle: &Particle) -> Cell {
+        let mut cell = particle.position().map(|axis| {
+            let index = (axis / self.cell_size).floor() as i64;
+            match self.wrap {
+                // Rounding can put a particle at the far wall one cell past the end
+                Some(per_side) => index.min(per_side - 1),
+                None => index,
+            }
+        });
+        if self.flat {
+            cell[2] = 0;
+        }
+        cell
+    }
+
+    // Get the width of each cell
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    // Get the occupied cells in a fixed order, so the pair search can be
+    // split between workers the same way on every grid built from the same particles
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut cells = self.cells.keys().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        cells
+    }
+
+    // Call `f` with every candidate pair (i, j), i != j, exactly once
+    pub fn for_each_candidate_pair<F: FnMut(usize, usize)>(&self, mut f: F) {
+        for &cell in self.cells.keys() {
+            self.for_each_pair_from(cell, &mut f);
+        }
+    }
+
+    // Call `f` with the candidate pairs owned by one cell: pairs inside the cell
+    // and pairs with its forward neighbours. Over all cells this covers every
+    // candidate pair exactly once.
+    pub fn for_each_pair_from<F: FnMut(usize, usize)>(&self, cell: Cell, mut f: F) {
+        let members = match self.cells.get(&cell) {
+            Some(members) => members,
+            None => return,
+        };
+        for (k, &i) in members.iter().enumerate() {
+            for &j in &members[k + 1..] {
+                f(i, j);
+            }
+        }
+        'neighbours: for step in self.neighbours {
+            let mut neighbour = [0; 3];
+            for axis in 0..3 {
+                // Far enough out the cell index saturates, and nothing lies past it
+                let Some(index) = cell[axis].checked_add(step[axis]) else {
+                    continue 'neighbours;
+                };
+                neighbour[axis] = match self.wrap {
+                    Some(per_side) => index.rem_euclid(per_side),
+                    None => index,
+                };
+            }
+            if let Some(others) = self.cells.get(&neighbour) {
+                for &i in members {
+                    for &j in others {
+                        f(i, j);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+use crate::{ConfigError, Particle, SimulationConfig};
+
+// How many random positions Poisson-disc placement tries for each particle
+// before giving up on the enclosure as too crowded
+const POISSON_DISC_TRIES: usize = 10_000;
+
+// Where new particles start in the enclosure
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    #[default]
+    Uniform, // Independently uniform over the enclosure
+    Lattice,   // A square (cubic) grid, its sites taken in random order
+    Hexagonal, // Hexagonal packing, likewise; stacked layers in 3D
+    // Gaussian blobs of std dev `spread` round random centres
+    Clusters {
+        count: usize,
+        spread: f32,
+    },
+    PoissonDisc,   // Uniform, but redrawn until nothing overlaps
+    File(PathBuf), // Positions read from a CSV file, one row per particle
+}
+
+impl Layout {
+    // The forms accepted on the command line
+    pub const FORMS: &'static [&'static str] = &[
+        "uniform",
+        "lattice",
+        "hexagonal",
+        "clusters:N:SD",
+        "poisson-disc",
+        "file:PATH",
+    ];
+
+    // Check that the layout's settings make sense
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        match *self {
+            Layout::Clusters { count, spread }
+                if count == 0 || !spread.is_finite() || spread < 0.0 =>
+            {
+                Err(ConfigError::Invalid(format!(
+                    "clusters need at least one cluster and a non-negative spread, got {}",
+                    self
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Move freshly created particles, in order of id, to their starting
+    // positions. Everything else about them is kept. Uniform leaves the
+    // positions they were created with and draws nothing from `rng`.
+    pub fn place<R: Rng + ?Sized>(
+        &self,
+        particles: &mut [Particle],
+        config: &SimulationConfig,
+        rng: &mut R,
+    ) -> Result<(), ConfigError> {
+        let (size, dimensions) = (config.enclosure_size, config.dimensions);
+        match self {
+            Layout::Uniform => {}
+            Layout::Lattice => {
+                // The smallest grid with room for everyone, with each particle
+                // in the middle of its cell
+                let side = cells_per_axis(particles.len(), |side| side.pow(dimensions as u32));
+                let spacing = size / side as f32;
+                let sites = shuffled_sites(particles.len(), rng);
+                for (particle, &site) in particles.iter_mut().zip(&sites) {
+                    let mut position = [0.0; 3];
+                    let mut index = site;
+                    for axis in position.iter_mut().take(dimensions) {
+                        *axis = ((index % side) as f32 + 0.5) * spacing;
+                        index /= side;
+                    }
+                    particle.set_position(position);
+                }
+            }
+            Layout::Hexagonal => {
+                // Rows of `columns` particles a spacing apart, alternate rows
+                // shifted by half a spacing. Odd layers in 3D sit over the
+                // hollows of the layer below, as in hexagonal close packing.
+                let row_height = 3f32.sqrt() / 2.0;
+                let layer_height = (2.0f32 / 3.0).sqrt();
+                let fit = |columns: usize, height: f32| (columns as f32 / height) as usize;
+                let columns = cells_per_axis(particles.len(), |columns| {
+                    let layers = match dimensions {
+                        2 => 1,
+                        _ => fit(columns, layer_height),
+                    };
+                    columns * fit(columns, row_height) * layers
+                });
+                let rows = fit(columns, row_height);
+                let spacing = size / columns as f32;
+                let sites = shuffled_sites(particles.len(), rng);
+                for (particle, &i) in particles.iter_mut().zip(&sites) {
+                    let (column, row, layer) =
+                        (i % columns, i / columns % rows, i / columns / rows);
+                    let shift = 0.5 * (row % 2) as f32 + 0.5 * (layer % 2) as f32;
+                    let mut position = [
+                        (column as f32 + 0.25 + shift.fract()) * spacing,
+                        (row as f32 + 0.5 + (layer % 2) as f32 / 3.0) * row_height * spacing,
+                        0.0,
+                    ];
+                    if dimensions == 3 {
+                        position[2] = (layer as f32 + 0.5) * layer_height * spacing;
+                    }
+                    particle.set_position(position);
+                }
+            }
+            Layout::Clusters { count, spread } => {
+                let centres = (0..*count)
+                    .map(|_| {
+                        let mut centre = [0.0; 3];
+                        for axis in centre.iter_mut().take(dimensions) {
+                            *axis = rng.random::<f32>() * size;
+                        }
+                        centre
+                    })
+                    .collect::<Vec<_>>();
+                // Particles are dealt round the clusters in turn
+                for (i, particle) in particles.iter_mut().enumerate() {
+                    let mut position = centres[i % count];
+                    for axis in position.iter_mut().take(dimensions) {
+                        *axis += spread * rng.sample::<f32, _>(StandardNormal);
+                    }
+                    particle.set_position(position);
+                    particle.settle(config);
+                }
+            }
+            Layout::PoissonDisc => {
+                // Dart throwing: each particle is redrawn until it overlaps
+                // none of those placed before it, whatever their species
+                for i in 0..particles.len() {
+                    let (placed, rest) = particles.split_at_mut(i);
+                    let particle = &mut rest[0];
+                    let mut tries = 0;
+                    while placed.iter().any(|other| particle.overlaps(other, config)) {
+                        tries += 1;
+                        if tries == POISSON_DISC_TRIES {
+                            return Err(ConfigError::Invalid(format!(
+                                "no room for particle {} without overlaps; the enclosure is too \
+                                 crowded for a poisson-disc layout",
+                                particle.id()
+                            )));
+                        }
+                        let mut position = [0.0; 3];
+                        for axis in position.iter_mut().take(dimensions) {
+                            *axis = rng.random::<f32>() * size;
+                        }
+                        particle.set_position(position);
+                    }
+                }
+            }
+            Layout::File(path) => {
+                let positions = read_positions(path, dimensions)?;
+                if positions.len() != particles.len() {
+                    return Err(ConfigError::Invalid(format!(
+                        "{} has {} positions for {} particles",
+                        path.display(),
+                        positions.len(),
+                        particles.len()
+                    )));
+                }
+                for (particle, position) in particles.iter_mut().zip(positions) {
+                    particle.set_position(position);
+                    if !particle.is_inside(config) {
+                        return Err(ConfigError::Invalid(format!(
+                            "{} puts particle {} outside the enclosure",
+                            path.display(),
+                            particle.id()
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Layout {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::InvalidValue {
+            key: "--layout".to_string(),
+            value: text.to_string(),
+        };
+        if let Some(path) = text.strip_prefix("file:") {
+            return Ok(Layout::File(PathBuf::from(path)));
+        }
+        match text.split(':').collect::<Vec<_>>().as_slice() {
+            ["uniform"] => Ok(Layout::Uniform),
+            ["lattice"] => Ok(Layout::Lattice),
+            ["hexagonal"] => Ok(Layout::Hexagonal),
+            ["clusters", count, spread] => Ok(Layout::Clusters {
+                count: count.parse().map_err(|_| invalid())?,
+                spread: spread.parse().map_err(|_| invalid())?,
+            }),
+            ["poisson-disc"] => Ok(Layout::PoissonDisc),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Uniform => write!(f, "uniform"),
+            Layout::Lattice => write!(f, "lattice"),
+            Layout::Hexagonal => write!(f, "hexagonal"),
+            Layout::Clusters { count, spread } => write!(f, "clusters:{}:{}", count, spread),
+            Layout::PoissonDisc => write!(f, "poisson-disc"),
+            Layout::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+// Find the smallest number of cells along an axis for which `capacity`
+// holds at least `particles`
+fn cells_per_axis(particles: usize, capacity: impl Fn(usize) -> usize) -> usize {
+    let mut cells = 1;
+    while capacity(cells) < particles {
+        cells += 1;
+    }
+    cells
+}
+
+// Number the first `count` sites of a packed layout in a random order. The
+// species of a mixture are created one after another, so filling the sites
+// in order would give each species a block of its own.
+fn shuffled_sites<R: Rng + ?Sized>(count: usize, rng: &mut R) -> Vec<usize> {
+    let mut sites = (0..count).collect::<Vec<_>>();
+    sites.shuffle(rng);
+    sites
+}
+
+// Read positions from a CSV file with a header row naming its columns. The
+// x and y columns are needed, and a z column too in 3D; any others, such as
+// those of a trajectory CSV, are ignored.
+fn read_positions(path: &Path, dimensions: usize) -> Result<Vec<[f32; 3]>, ConfigError> {
+    let name = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|err| ConfigError::Io(name.clone(), err))?;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header = lines.next().unwrap_or_default();
+    let columns = ["x", "y", "z"]
+        .iter()
+        .take(dimensions)
+        .map(|axis| {
+            header
+                .split(',')
+                .position(|column| column.trim() == *axis)
+                .ok_or_else(|| ConfigError::Invalid(format!("{} has no '{}' column", name, axis)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    lines
+        .enumerate()
+        .map(|(row, line)| {
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+            let mut position = [0.0; 3];
+            for (axis, &column) in columns.iter().enumerate() {
+                position[axis] = fields
+                    .get(column)
+                    .and_then(|field| field.parse().ok())
+                    .ok_or_else(|| {
+                        ConfigError::Invalid(format!("{}: bad position on row {}", name, row + 1))
+                    })?;
+            }
+            Ok(position)
+        })
+        .collect()
+}
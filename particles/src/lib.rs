@@ -0,0 +1,58 @@
+// Shared particle simulation library used by the threaded simulators
+
+mod atomic;
+mod boundary;
+mod cli;
+mod config;
+mod driver;
+mod events;
+mod grid;
+mod layout;
+mod motion;
+mod particle;
+mod population;
+mod radius;
+mod rates;
+mod rng;
+mod snapshot;
+mod species;
+mod strategy;
+mod system;
+mod trajectory;
+
+pub use atomic::AtomicParticles;
+pub use boundary::Boundary;
+pub use cli::Cli;
+pub use config::{ConfigError, SimulationConfig, DEFAULT_SPECIES};
+pub use driver::run_simulator;
+pub use events::{CollisionEvent, Contact, EventFormat, EventWriter};
+pub use grid::{Cell, CollisionMethod, SpatialGrid};
+pub use layout::Layout;
+pub use motion::MovementModel;
+pub use particle::Particle;
+pub use population::{Population, PopulationRow};
+pub use radius::RadiusDistribution;
+pub use rates::{
+    analyse_rates, contact_probability, expected_contact_probability, CollisionRates, RateAnalysis,
+    Summary,
+};
+pub use rng::{stream_rng, SimRng};
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use species::{Interaction, InteractionRule, Reaction, Species};
+pub use strategy::{run, run_with_checkpoints, RunOptions, RunReport, RwFairness, Strategy};
+pub use system::ParticleSystem;
+pub use trajectory::{
+    is_compressed, Frame, TrajectoryFile, TrajectoryFormat, TrajectoryInput, TrajectoryReader,
+    TrajectoryWriter, TRAJECTORY_MAGIC, TRAJECTORY_VERSION,
+};
+
+// Default values for SimulationConfig
+pub const NUM_OF_PARTICLES: usize = 100;
+pub const ENCLOSURE_SIZE: f32 = 10.0; // 10x10 enclosure
+pub const NUM_OF_STEPS: u64 = 1000; // Move and check particles 1000 times
+pub const COLLISION_THRESHOLD: f32 = 0.2; // Particles collide when their centres are closer than this
+pub const DT: f32 = 1.0; // Length of one step
+pub const DIFFUSION: f32 = 1.0 / 6.0; // Brownian steps spread as far as random-walk ones at dt = 1
+pub const INITIAL_SPEED: f32 = 1.0; // Speed of ballistic particles at the start
+pub const PARTICLE_MASS: f32 = 1.0;
+pub const PARTICLE_RADIUS: f32 = COLLISION_THRESHOLD / 2.0; // Two radii make the threshold
@@ -0,0 +1,45 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+
+// How Particle::move_particle advances a particle over one step of length dt
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MovementModel {
+    RandomWalk, // A fresh velocity every step, uniform in [-1, 1] on each axis
+    Brownian,   // Gaussian displacements with variance 2 * diffusion * dt per axis
+    Ballistic,  // Straight-line motion at a constant velocity between collisions
+}
+
+impl MovementModel {
+    pub const NAMES: &'static [&'static str] = &["random-walk", "brownian", "ballistic"];
+}
+
+impl FromStr for MovementModel {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "random-walk" => Ok(MovementModel::RandomWalk),
+            "brownian" => Ok(MovementModel::Brownian),
+            "ballistic" => Ok(MovementModel::Ballistic),
+            _ => Err(ConfigError::InvalidValue {
+                key: "--movement".to_string(),
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for MovementModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovementModel::RandomWalk => write!(f, "random-walk"),
+            MovementModel::Brownian => write!(f, "brownian"),
+            MovementModel::Ballistic => write!(f, "ballistic"),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::f32::consts::TAU;
+
+use rand::{Rng, RngExt};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+use crate::{Boundary, Interaction, MovementModel, SimulationConfig, Species};
+
+// Define the Particle struct. Vectors always have three components; in a 2D
+// system the z components stay at zero.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Particle {
+    id: u64,        // Stable identifier, unchanged as the particle moves
+    species: usize, // Index into the config's species list
+    position: [f32; 3],
+    velocity: [f32; 3], // Velocity over the last step, or the next one for ballistic motion
+    mass: f32,
+    radius: f32,
+}
+
+impl Particle {
+    // Create a new particle of the first species in the config
+    pub fn new<R: Rng + ?Sized>(id: u64, config: &SimulationConfig, rng: &mut R) -> Self {
+        Self::of_species(id, 0, &config.species_list()[0], config, rng)
+    }
+
+    // Create a new particle of species number `index`, described by `species`,
+    // with random initial position within the enclosure and a radius drawn
+    // from the species' distribution.
+    // Ballistic particles also start at `initial_speed` in a random direction;
+    // the other models pick a new velocity every step, so start at rest.
+    pub fn of_species<R: Rng + ?Sized>(
+        id: u64,
+        index: usize,
+        species: &Species,
+        config: &SimulationConfig,
+        rng: &mut R,
+    ) -> Self {
+        let mut position = [0.0; 3];
+        for axis in position.iter_mut().take(config.dimensions) {
+            *axis = rng.random::<f32>() * config.enclosure_size;
+        }
+        let velocity = match config.movement {
+            MovementModel::Ballistic => {
+                random_direction(config.dimensions, rng).map(|axis| species.initial_speed * axis)
+            }
+            _ => [0.0; 3],
+        };
+        Particle {
+            id,
+            species: index,
+            position,
+            velocity,
+            mass: species.mass,
+            radius: species.radius.sample(rng),
+        }
+    }
+
+    // Create a point particle of unit mass at rest at a known position in the xy plane
+    pub fn from_position(id: u64, x: f32, y: f32) -> Self {
+        Self::at(id, [x, y, 0.0])
+    }
+
+    // Create a point particle of unit mass at rest at a known position
+    pub fn at(id: u64, position: [f32; 3]) -> Self {
+        Particle {
+            id,
+            species: 0,
+            position,
+            velocity: [0.0; 3],
+            mass: 1.0,
+            radius: 0.0,
+        }
+    }
+
+    // Set the velocity
+    pub fn with_velocity(mut self, velocity: [f32; 3]) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    // Set the species, by its index in the config's species list
+    pub fn with_species(mut self, species: usize) -> Self {
+        self.species = species;
+        self
+    }
+
+    // Set the mass
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    // Set the radius
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    // Advance the particle by one step of `config.dt` using the configured
+    // movement model, then apply the enclosure's boundary
+    pub fn move_particle<R: Rng + ?Sized>(&mut self, config: &SimulationConfig, rng: &mut R) {
+        let axes = config.dimensions;
+        match config.movement {
+            MovementModel::RandomWalk => {
+                for axis in self.velocity.iter_mut().take(axes) {
+                    *axis = (rng.random::<f32>() - 0.5) * 2.0; // Random value between -1 and 1
+                }
+            }
+            MovementModel::Brownian => {
+                // A displacement with standard deviation sqrt(2 D dt), as a velocity over dt
+                let diffusion = config.diffusion_of(self.species);
+                let sigma = (2.0 * diffusion / config.dt).sqrt();
+                for axis in self.velocity.iter_mut().take(axes) {
+                    *axis = sigma * rng.sample::<f32, _>(StandardNormal);
+                }
+            }
+            MovementModel::Ballistic => {}
+        }
+
+        for axis in 0..axes {
+            self.position[axis] += self.velocity[axis] * config.dt;
+        }
+        self.confine(config);
+    }
+
+    // Apply the boundary to a particle that may have left the enclosure.
+    // A particle that crosses an absorbing wall is left outside for the
+    // system to remove.
+    fn confine(&mut self, config: &SimulationConfig) {
+        let size = config.enclosure_size;
+        for axis in 0..config.dimensions {
+            let (position, velocity) = (self.position[axis], self.velocity[axis]);
+            match config.boundary {
+                // Stop at the wall, dropping any motion into it so a ballistic
+                // particle does not stay pinned there
+                Boundary::Clamp => {
+                    self.position[axis] = position.clamp(0.0, size);
+                    if (position < 0.0 && velocity < 0.0) || (position > size && velocity > 0.0) {
+                        self.velocity[axis] = 0.0;
+                    }
+                }
+                Boundary::Reflect => {
+                    let (position, velocity) = reflect(position, velocity, size);
+                    self.position[axis] = position;
+                    self.velocity[axis] = velocity;
+                }
+                Boundary::Periodic => self.position[axis] = config.boundary.wrap(position, size),
+                Boundary::Absorbing => {}
+            }
+        }
+    }
+
+    // Put a particle that was pushed out of the enclosure back in without
+    // touching its velocity. Pushes never absorb a particle.
+    pub(crate) fn settle(&mut self, config: &SimulationConfig) {
+        let size = config.enclosure_size;
+        for axis in self.position.iter_mut().take(config.dimensions) {
+            *axis = match config.boundary {
+                Boundary::Periodic => config.boundary.wrap(*axis, size),
+                _ => axis.clamp(0.0, size),
+            };
+        }
+    }
+
+    // Check whether the particle is within the enclosure. Only a particle
+    // that has crossed an absorbing wall can be outside.
+    pub fn is_inside(&self, config: &SimulationConfig) -> bool {
+        let size = config.enclosure_size;
+        self.position.iter().all(|axis| (0.0..=size).contains(axis))
+    }
+
+    // Check if this particle collides with another: the two overlap, and the
+    // config does not let their species pass through each other
+    pub fn collide(&self, other: &Particle, config: &SimulationConfig) -> bool {
+        self.overlaps(other, config)
+            && config.interaction(self.species, other.species) != Interaction::Ignore
+    }
+
+    // Check if this particle overlaps another, whatever their species
+    pub fn overlaps(&self, other: &Particle, config: &SimulationConfig) -> bool {
+        length(self.offset_to(other, config)) < self.radius + other.radius
+    }
+
+    // Resolve a contact with another particle. The two are pushed apart along
+    // the line between their centres until they no longer touch, each moving in
+    // proportion to the other's mass, and if they are approaching they exchange
+    // momentum along that line. `restitution` scales their closing speed: 1 is
+    // an elastic collision, 0 leaves them moving together.
+    pub fn resolve_collision(
+        &mut self,
+        other: &mut Particle,
+        config: &SimulationConfig,
+        restitution: f32,
+    ) {
+        let offset = self.offset_to(other, config);
+        let distance = length(offset);
+        // Particles on top of each other have no line between them; pick one
+        let normal = if distance > 0.0 {
+            offset.map(|axis| axis / distance)
+        } else {
+            [1.0, 0.0, 0.0]
+        };
+        let (w1, w2) = (1.0 / self.mass, 1.0 / other.mass);
+
+        let overlap = self.radius + other.radius - distance;
+        if overlap > 0.0 {
+            let (push1, push2) = (overlap * w1 / (w1 + w2), overlap * w2 / (w1 + w2));
+            for (axis, n) in normal.iter().enumerate() {
+                self.position[axis] -= n * push1;
+                other.position[axis] += n * push2;
+            }
+            self.settle(config);
+            other.settle(config);
+        }
+
+        let closing = (0..3)
+            .map(|axis| (other.velocity[axis] - self.velocity[axis]) * normal[axis])
+            .sum::<f32>();
+        if closing < 0.0 {
+            let impulse = -(1.0 + restitution) * closing / (w1 + w2);
+            for (axis, n) in normal.iter().enumerate() {
+                self.velocity[axis] -= impulse * w1 * n;
+                other.velocity[axis] += impulse * w2 * n;
+            }
+        }
+    }
+
+    // Combine this particle and another into one of species `species`, as if
+    // they had stuck together: the new particle has their total mass and
+    // momentum, sits at their centre of mass and has their combined volume
+    // (area in 2D).
+    pub fn merge(
+        &self,
+        other: &Particle,
+        id: u64,
+        species: usize,
+        config: &SimulationConfig,
+    ) -> Particle {
+        let mass = self.mass + other.mass;
+        let offset = self.offset_to(other, config);
+        let (p1, p2) = (self.momentum(), other.momentum());
+        let mut merged = Particle {
+            id,
+            species,
+            position: [0.0; 3],
+            velocity: [0.0; 3],
+            mass,
+            radius: 0.0,
+        };
+        for axis in 0..3 {
+            merged.position[axis] = self.position[axis] + offset[axis] * other.mass / mass;
+            merged.velocity[axis] = (p1[axis] + p2[axis]) / mass;
+        }
+        let power = config.dimensions as f32;
+        merged.radius = (self.radius.powf(power) + other.radius.powf(power)).powf(1.0 / power);
+        merged.settle(config);
+        merged
+    }
+
+    // Get the vector from this particle's centre to another's. With periodic
+    // walls this is the shortest way round, possibly through a wall.
+    pub fn offset_to(&self, other: &Particle, config: &SimulationConfig) -> [f32; 3] {
+        let size = config.enclosure_size;
+        let mut offset = [0.0; 3];
+        for (axis, value) in offset.iter_mut().enumerate() {
+            *value = config
+                .boundary
+                .offset(self.position[axis], other.position[axis], size);
+        }
+        offset
+    }
+
+    // Get the straight-line distance between the centres of this particle and
+    // another, ignoring any wrap-around
+    pub fn distance(&self, other: &Particle) -> f32 {
+        let mut offset = [0.0; 3];
+        for (axis, value) in offset.iter_mut().enumerate() {
+            *value = other.position[axis] - self.position[axis];
+        }
+        length(offset)
+    }
+
+    // Get the particle's identifier
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // Get the index of the particle's species in the config's species list
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
+    // Get the position of the particle in the xy plane
+    pub fn get_position(&self) -> (f32, f32) {
+        (self.position[0], self.position[1])
+    }
+
+    // Get the full position of the particle
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    // Move the particle to a new position, keeping everything else
+    pub fn set_position(&mut self, position: [f32; 3]) {
+        self.position = position;
+    }
+
+    // Get the velocity of the particle
+    pub fn velocity(&self) -> [f32; 3] {
+        self.velocity
+    }
+
+    // Get the momentum of the particle
+    pub fn momentum(&self) -> [f32; 3] {
+        self.velocity.map(|axis| self.mass * axis)
+    }
+
+    // Get the kinetic energy of the particle
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass * self.velocity.iter().map(|axis| axis * axis).sum::<f32>()
+    }
+
+    // Get the mass of the particle
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    // Get the radius of the particle
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+// Get the length of a vector
+fn length(vector: [f32; 3]) -> f32 {
+    vector.iter().map(|axis| axis * axis).sum::<f32>().sqrt()
+}
+
+// Pick a unit vector in a uniformly random direction, in the xy plane for a 2D system
+fn random_direction<R: Rng + ?Sized>(dimensions: usize, rng: &mut R) -> [f32; 3] {
+    let angle = rng.random::<f32>() * TAU;
+    if dimensions == 2 {
+        return [angle.cos(), angle.sin(), 0.0];
+    }
+    // Uniform on the sphere: z uniform in [-1, 1], then a uniform angle around it
+    let z = rng.random::<f32>() * 2.0 - 1.0;
+    let across = (1.0 - z * z).sqrt();
+    [across * angle.cos(), across * angle.sin(), z]
+}
+
+// Bounce a coordinate that has passed a wall back inside, pointing its
+// velocity away from that wall
+fn reflect(position: f32, velocity: f32, size: f32) -> (f32, f32) {
+    if position < 0.0 {
+        ((-position).min(size), velocity.abs())
+    } else if position > size {
+        ((2.0 * size - position).max(0.0), -velocity.abs())
+    } else {
+        (position, velocity)
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Particle, SimulationConfig};
+
+// A step, then the count of each species after it
+pub type PopulationRow = (u64, Vec<usize>);
+
+// The number of particles of each species, step by step. Only the first and
+// latest rows are kept unless the whole series is asked for, so a long run
+// does not grow a row a step. The series is never saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Population {
+    species: Vec<String>,          // Species names, in config order
+    first: Option<PopulationRow>,  // The first row recorded
+    latest: Option<PopulationRow>, // The last row recorded
+    pair_steps: f64,               // Pairs of particles, summed over later rows
+    #[serde(skip)]
+    history: Option<Vec<PopulationRow>>, // Every row since the series was asked for
+}
+
+impl Population {
+    // Start an empty series for the species in the config
+    pub fn new(config: &SimulationConfig) -> Self {
+        Population {
+            species: config
+                .species_list()
+                .into_iter()
+                .map(|species| species.name)
+                .collect(),
+            first: None,
+            latest: None,
+            pair_steps: 0.0,
+            history: None,
+        }
+    }
+
+    // Keep every row from now on, starting with the latest
+    pub fn keep_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(self.latest.iter().cloned().collect());
+        }
+    }
+
+    // Get a copy without the kept series, as much as resuming a run needs
+    pub fn without_history(&self) -> Self {
+        Population {
+            species: self.species.clone(),
+            first: self.first.clone(),
+            latest: self.latest.clone(),
+            pair_steps: self.pair_steps,
+            history: None,
+        }
+    }
+
+    // Count the particles of each species after `step`
+    pub fn record(&mut self, step: u64, particles: &[Particle]) {
+        let mut counts = vec![0; self.species.len()];
+        for particle in particles {
+            counts[particle.species()] += 1;
+        }
+        if self.first.is_none() {
+            self.first = Some((step, counts.clone()));
+        } else {
+            let total = particles.len() as f64;
+            self.pair_steps += total * (total - 1.0) / 2.0;
+        }
+        if let Some(history) = &mut self.history {
+            history.push((step, counts.clone()));
+        }
+        self.latest = Some((step, counts));
+    }
+
+    // Add a species, with no particles in any row so far
+    pub fn add_species(&mut self, name: &str) {
+        self.species.push(name.to_string());
+        let history = self.history.iter_mut().flatten();
+        for (_, counts) in self.first.iter_mut().chain(&mut self.latest).chain(history) {
+            counts.push(0);
+        }
+    }
+
+    // Get the species names, in the order of each row's counts
+    pub fn species(&self) -> &[String] {
+        &self.species
+    }
+
+    // Get every row kept since keep_history, oldest first; none without it
+    pub fn counts(&self) -> &[PopulationRow] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    // Get the first row recorded
+    pub fn first(&self) -> Option<&PopulationRow> {
+        self.first.as_ref()
+    }
+
+    // Get the last row recorded
+    pub fn latest(&self) -> Option<&PopulationRow> {
+        self.latest.as_ref()
+    }
+
+    // Get the pairs of particles, summed over every row after the first
+    pub fn pair_steps(&self) -> f64 {
+        self.pair_steps
+    }
+
+    // Get the latest count of each species, by name
+    pub fn final_counts(&self) -> Vec<(String, usize)> {
+        let counts = self.latest.as_ref().map(|(_, counts)| counts.as_slice());
+        self.species
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), counts.map_or(0, |counts| counts[i])))
+            .collect()
+    }
+
+    // Write the series to a CSV file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut out)?;
+        out.flush()
+    }
+
+    // Write the kept series as CSV: the step, the total, then one column per
+    // species
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "step,total,{}", self.species.join(","))?;
+        for (step, counts) in self.counts() {
+            let total = counts.iter().sum::<usize>();
+            write!(out, "{},{}", step, total)?;
+            for count in counts {
+                write!(out, ",{}", count)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
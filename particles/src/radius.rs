@@ -0,0 +1,88 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{Rng, RngExt};
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+
+// How the radii of new particles are chosen
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RadiusDistribution {
+    Fixed(f32),                         // Every particle gets the same radius
+    Uniform { min: f32, max: f32 },     // Uniform in [min, max)
+    Normal { mean: f32, std_dev: f32 }, // Gaussian, with negative draws taken as zero
+}
+
+impl RadiusDistribution {
+    // The forms accepted on the command line
+    pub const FORMS: &'static [&'static str] = &["R", "uniform:MIN:MAX", "normal:MEAN:SD"];
+
+    // Draw a radius. A fixed radius draws nothing from `rng`.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        match *self {
+            RadiusDistribution::Fixed(radius) => radius,
+            RadiusDistribution::Uniform { min, max } => min + rng.random::<f32>() * (max - min),
+            RadiusDistribution::Normal { mean, std_dev } => {
+                (mean + std_dev * rng.sample::<f32, _>(StandardNormal)).max(0.0)
+            }
+        }
+    }
+
+    // Check that the distribution only gives finite, non-negative radii
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let valid = |value: f32| value.is_finite() && value >= 0.0;
+        let ok = match *self {
+            RadiusDistribution::Fixed(radius) => valid(radius),
+            RadiusDistribution::Uniform { min, max } => valid(min) && valid(max) && min <= max,
+            RadiusDistribution::Normal { mean, std_dev } => valid(mean) && valid(std_dev),
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(format!(
+                "radii must be finite and non-negative, with min <= max, got {}",
+                self
+            )))
+        }
+    }
+}
+
+impl FromStr for RadiusDistribution {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConfigError::InvalidValue {
+            key: "--radius".to_string(),
+            value: text.to_string(),
+        };
+        let parts = text.split(':').collect::<Vec<_>>();
+        let number = |part: &str| part.parse::<f32>().map_err(|_| invalid());
+        match parts.as_slice() {
+            [radius] => Ok(RadiusDistribution::Fixed(number(radius)?)),
+            ["uniform", min, max] => Ok(RadiusDistribution::Uniform {
+                min: number(min)?,
+                max: number(max)?,
+            }),
+            ["normal", mean, std_dev] => Ok(RadiusDistribution::Normal {
+                mean: number(mean)?,
+                std_dev: number(std_dev)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for RadiusDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RadiusDistribution::Fixed(radius) => write!(f, "{}", radius),
+            RadiusDistribution::Uniform { min, max } => write!(f, "uniform:{}:{}", min, max),
+            RadiusDistribution::Normal { mean, std_dev } => {
+                write!(f, "normal:{}:{}", mean, std_dev)
+            }
+        }
+    }
+}
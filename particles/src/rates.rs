@@ -0,0 +1,282 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    run, Boundary, ConfigError, Interaction, Layout, ParticleSystem, RadiusDistribution,
+    RunOptions, SimulationConfig,
+};
+
+// Two-sided 95% critical values of Student's t, by degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+// The collision rates of one run
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CollisionRates {
+    pub seed: u64,
+    pub steps: u64,        // Steps completed
+    pub particles: usize,  // Particles at the start
+    pub collisions: usize, // Pairs entering contact
+    pub overlaps: usize,   // Pairs in contact, summed over every step
+    pub pair_steps: f64,   // Pairs of particles, summed over every step
+}
+
+impl CollisionRates {
+    // Measure the rates of a system that has run from step 0
+    pub fn measure(system: &ParticleSystem) -> Self {
+        let population = system.population();
+        let particles = population
+            .first()
+            .map_or(0, |(_, counts)| counts.iter().sum());
+        CollisionRates {
+            seed: system.seed(),
+            steps: system.step_count(),
+            particles,
+            collisions: system.get_collision_count(),
+            overlaps: system.get_overlap_count(),
+            pair_steps: population.pair_steps(),
+        }
+    }
+
+    // Get the collisions per step
+    pub fn per_step(&self) -> f64 {
+        self.collisions as f64 / self.steps.max(1) as f64
+    }
+
+    // Get the collisions each particle takes part in over the run. Every
+    // collision involves two particles.
+    pub fn per_particle(&self) -> f64 {
+        2.0 * self.collisions as f64 / self.particles.max(1) as f64
+    }
+
+    // Get the fraction of pairs of particles in contact, over every step
+    pub fn contact_probability(&self) -> f64 {
+        match self.pair_steps {
+            pairs if pairs > 0.0 => self.overlaps as f64 / pairs,
+            _ => 0.0,
+        }
+    }
+}
+
+// The mean and spread of one measurement over repeated runs
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Summary {
+    pub runs: usize,
+    pub mean: f64,
+    pub variance: f64, // Sample variance, zero for a single run
+}
+
+impl Summary {
+    // Summarise the values measured in each run
+    pub fn of(values: &[f64]) -> Self {
+        let runs = values.len();
+        let mean = values.iter().sum::<f64>() / runs.max(1) as f64;
+        let variance = match runs {
+            0 | 1 => 0.0,
+            _ => values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (runs - 1) as f64,
+        };
+        Summary {
+            runs,
+            mean,
+            variance,
+        }
+    }
+
+    // Get the standard error of the mean
+    pub fn std_error(&self) -> f64 {
+        (self.variance / self.runs.max(1) as f64).sqrt()
+    }
+
+    // Get the 95% confidence interval for the mean, from Student's t. A single
+    // run gives no interval, only its value.
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        let t = match self.runs {
+            0 | 1 => 0.0,
+            runs if runs <= T_95.len() + 1 => T_95[runs - 2],
+            runs => {
+                // The first correction to the normal value is close enough beyond the table
+                let (z, df): (f64, f64) = (1.959964, (runs - 1) as f64);
+                z + (z.powi(3) + z) / (4.0 * df)
+            }
+        };
+        let half_width = t * self.std_error();
+        (self.mean - half_width, self.mean + half_width)
+    }
+}
+
+// Collision rates over repeated seeded runs, with the contact probability
+// theory expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateAnalysis {
+    pub runs: Vec<CollisionRates>,
+    pub per_step: Summary,
+    pub per_particle: Summary,
+    pub contact_probability: Summary,
+    pub expected_contact_probability: Option<f64>, // See expected_contact_probability
+}
+
+impl RateAnalysis {
+    // Summarise the rates of runs of a config
+    pub fn new(runs: Vec<CollisionRates>, config: &SimulationConfig) -> Self {
+        let summary = |rate: fn(&CollisionRates) -> f64| {
+            Summary::of(&runs.iter().map(rate).collect::<Vec<_>>())
+        };
+        RateAnalysis {
+            per_step: summary(CollisionRates::per_step),
+            per_particle: summary(CollisionRates::per_particle),
+            contact_probability: summary(CollisionRates::contact_probability),
+            expected_contact_probability: expected_contact_probability(config),
+            runs,
+        }
+    }
+
+    // Get how many standard errors the measured contact probability is from
+    // the expected one, if there is an expectation and a spread to measure by
+    pub fn deviation(&self) -> Option<f64> {
+        let expected = self.expected_contact_probability?;
+        let std_error = self.contact_probability.std_error();
+        (std_error > 0.0).then(|| (self.contact_probability.mean - expected) / std_error)
+    }
+
+    // Check whether the expected contact probability lies within the 95%
+    // confidence interval of the measured one
+    pub fn agrees(&self) -> Option<bool> {
+        let expected = self.expected_contact_probability?;
+        let (low, high) = self.contact_probability.confidence_interval();
+        Some((low..=high).contains(&expected))
+    }
+}
+
+impl fmt::Display for RateAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seeds = self.runs.iter().map(|run| run.seed);
+        if let (Some(first), Some(last)) = (seeds.clone().min(), seeds.max()) {
+            writeln!(f, "Runs: {} (seeds {} to {})", self.runs.len(), first, last)?;
+        }
+        for (name, summary) in [
+            ("Collisions per step", &self.per_step),
+            ("Collisions per particle", &self.per_particle),
+            ("Contact probability", &self.contact_probability),
+        ] {
+            let (low, high) = summary.confidence_interval();
+            writeln!(
+                f,
+                "{}: mean {:.6}, variance {:.6e}, 95% CI [{:.6}, {:.6}]",
+                name, summary.mean, summary.variance, low, high
+            )?;
+        }
+        match (self.expected_contact_probability, self.deviation()) {
+            (Some(expected), Some(deviation)) => write!(
+                f,
+                "Expected contact probability: {:.6} ({:+.2} standard errors, {} the 95% CI)",
+                expected,
+                deviation,
+                if self.agrees() == Some(true) {
+                    "inside"
+                } else {
+                    "outside"
+                }
+            ),
+            (Some(expected), None) => {
+                write!(f, "Expected contact probability: {:.6}", expected)
+            }
+            (None, _) => write!(
+                f,
+                "Expected contact probability: none; theory needs a uniform layout, reflect or \
+                 periodic walls, fixed radii and no restitution or reactions"
+            ),
+        }
+    }
+}
+
+// Run a config `runs` times with the given threading options and summarise
+// their rates. The runs are seeded one after another from the configured seed,
+// or from a random one.
+pub fn analyse_rates(
+    config: &SimulationConfig,
+    options: &RunOptions,
+    runs: usize,
+) -> Result<RateAnalysis, ConfigError> {
+    let first_seed = config.seed.unwrap_or_else(rand::random);
+    let mut rates = Vec::with_capacity(runs);
+    for run_index in 0..runs {
+        let config = SimulationConfig {
+            seed: Some(first_seed.wrapping_add(run_index as u64)),
+            ..config.clone()
+        };
+        let system = Arc::new(Mutex::new(ParticleSystem::try_new(config)?));
+        run(&system, options);
+        rates.push(CollisionRates::measure(&system.lock().unwrap()));
+    }
+    Ok(RateAnalysis::new(rates, config))
+}
+
+// Get the probability that two independent points, uniform in the enclosure,
+// are closer than `distance`. Periodic walls measure the short way round.
+// None if `distance` is too long for the closed form: over half the enclosure
+// with periodic walls, or over all of it otherwise.
+pub fn contact_probability(
+    distance: f64,
+    size: f64,
+    dimensions: usize,
+    boundary: Boundary,
+) -> Option<f64> {
+    let r = distance / size;
+    match (boundary, dimensions) {
+        // The ball around one point fits inside the torus
+        (Boundary::Periodic, _) if r > 0.5 => None,
+        (Boundary::Periodic, 2) => Some(PI * r.powi(2)),
+        (Boundary::Periodic, _) => Some(4.0 / 3.0 * PI * r.powi(3)),
+        // Otherwise the ball is cut off where the other point would be outside
+        (_, _) if r > 1.0 => None,
+        (_, 2) => Some(PI * r.powi(2) - 8.0 / 3.0 * r.powi(3) + 0.5 * r.powi(4)),
+        (_, _) => Some(
+            4.0 / 3.0 * PI * r.powi(3) - 1.5 * PI * r.powi(4) + 1.6 * r.powi(5) - r.powi(6) / 6.0,
+        ),
+    }
+}
+
+// Get the probability that a pair of particles is in contact at a step, for
+// particles that stay uniform and independent. That needs a uniform layout,
+// walls that keep particles uniform (reflect or periodic), a fixed radius for
+// each species, no collision response and no reactions; other configs, and
+// distances too long for contact_probability, have no expectation. Species
+// pairs that ignore each other never count as in contact.
+pub fn expected_contact_probability(config: &SimulationConfig) -> Option<f64> {
+    if config.layout != Layout::Uniform
+        || !matches!(config.boundary, Boundary::Reflect | Boundary::Periodic)
+        || config.restitution.is_some()
+        || config.has_reactions()
+    {
+        return None;
+    }
+    let species = config.species_list();
+    let mut expected = 0.0;
+    let mut pairs = 0.0;
+    for (a, first) in species.iter().enumerate() {
+        for (b, second) in species.iter().enumerate().skip(a) {
+            let (n, m) = (first.count as f64, second.count as f64);
+            let between = if a == b { n * (n - 1.0) / 2.0 } else { n * m };
+            pairs += between;
+            if between == 0.0 || config.interaction(a, b) == Interaction::Ignore {
+                continue;
+            }
+            let distance = match (first.radius, second.radius) {
+                (RadiusDistribution::Fixed(r1), RadiusDistribution::Fixed(r2)) => r1 + r2,
+                _ => return None,
+            };
+            expected += between
+                * contact_probability(
+                    distance as f64,
+                    config.enclosure_size as f64,
+                    config.dimensions,
+                    config.boundary,
+                )?;
+        }
+    }
+    (pairs > 0.0).then(|| expected / pairs)
+}
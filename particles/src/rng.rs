@@ -0,0 +1,12 @@
+use rand::rngs::Xoshiro256PlusPlus;
+use rand::SeedableRng;
+
+// The random number generator used for every draw in the simulation.
+// Xoshiro256++ is portable, so a seed gives the same stream on every machine.
+pub type SimRng = Xoshiro256PlusPlus;
+
+// Derive an independent stream from a seed.
+// Stream 0 belongs to the ParticleSystem itself, workers use 1, 2, 3...
+pub fn stream_rng(seed: u64, stream: u64) -> SimRng {
+    SimRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Particle, Population, SimRng, SimulationConfig};
+
+// Bumped whenever the snapshot layout changes; older snapshots are refused
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+// The full state of a ParticleSystem, enough to carry on exactly where it
+// stopped. Event sinks and worker pools are not part of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snapshot {
+    pub version: u32,
+    pub config: SimulationConfig,
+    pub seed: u64,
+    pub rng: SimRng,            // The system's own stream
+    pub move_rngs: Vec<SimRng>, // One stream per move worker
+    pub step_count: u64,
+    pub collision_count: usize,
+    pub overlap_count: usize,
+    pub next_id: u64,
+    pub particles: Vec<Particle>,
+    pub contacts: Vec<(u64, u64)>, // Pairs in contact at the last step, in order
+    pub species_collisions: Vec<((usize, usize), usize)>,
+    pub population: Population, // The first and latest rows, not the series
+}
+
+// Errors raised while loading a snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(String, io::Error),
+    Parse(String),
+    Version(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(path, err) => write!(f, "cannot read {}: {}", path, err),
+            SnapshotError::Parse(msg) => write!(f, "cannot parse snapshot: {}", msg),
+            SnapshotError::Version(version) => write!(
+                f,
+                "snapshot version {} is not supported (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Invalid(msg) => write!(f, "invalid snapshot: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl Snapshot {
+    // Load a snapshot saved as JSON
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| SnapshotError::Io(path.display().to_string(), err))?;
+        Self::from_json_str(&text)
+    }
+
+    // Parse a snapshot from JSON text, refusing other versions
+    pub fn from_json_str(text: &str) -> Result<Self, SnapshotError> {
+        // Check the version first, so an old layout gets a clear error
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } =
+            serde_json::from_str(text).map_err(|err| SnapshotError::Parse(err.to_string()))?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(version));
+        }
+        serde_json::from_str(text).map_err(|err| SnapshotError::Parse(err.to_string()))
+    }
+
+    // Save the snapshot as JSON. Floats are written with enough digits to
+    // read back exactly. The file is written alongside and then renamed over
+    // `path`, so a run killed while saving leaves the previous snapshot intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut out = BufWriter::new(File::create(&partial)?);
+        self.write_json(&mut out)?;
+        out.flush()?;
+        drop(out);
+        fs::rename(&partial, path)
+    }
+
+    // Write the snapshot as JSON
+    pub fn write_json<W: Write>(&self, out: W) -> io::Result<()> {
+        serde_json::to_writer(out, self).map_err(io::Error::from)
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ConfigError, RadiusDistribution, DIFFUSION, INITIAL_SPEED, PARTICLE_MASS, PARTICLE_RADIUS,
+};
+
+// One kind of particle in a mixture. Unset fields take the defaults for a
+// single-species system.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Species {
+    pub name: String,
+    pub count: usize, // Number of particles of this species at the start
+    pub mass: f32,
+    pub radius: RadiusDistribution,
+    pub diffusion: f32,     // Diffusion coefficient for Brownian motion
+    pub initial_speed: f32, // Starting speed for ballistic motion
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        Species {
+            name: String::new(),
+            count: 0,
+            mass: PARTICLE_MASS,
+            radius: RadiusDistribution::Fixed(PARTICLE_RADIUS),
+            diffusion: DIFFUSION,
+            initial_speed: INITIAL_SPEED,
+        }
+    }
+}
+
+impl Species {
+    // Reject a species the simulation cannot run with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |what: &str, value: f32| {
+            Err(ConfigError::Invalid(format!(
+                "species '{}': {} must not be negative, got {}",
+                self.name, what, value
+            )))
+        };
+        if self.name.is_empty() {
+            return Err(ConfigError::Invalid(
+                "every species needs a name".to_string(),
+            ));
+        }
+        // Names are written as columns of trajectory files
+        if self
+            .name
+            .chars()
+            .any(|c| c.is_whitespace() || c == ',' || c == '"')
+        {
+            return Err(ConfigError::Invalid(format!(
+                "species name '{}' must not contain spaces, commas or quotes",
+                self.name
+            )));
+        }
+        if !self.mass.is_finite() || self.mass <= 0.0 {
+            return Err(ConfigError::Invalid(format!(
+                "species '{}': mass must be positive, got {}",
+                self.name, self.mass
+            )));
+        }
+        if !self.diffusion.is_finite() || self.diffusion < 0.0 {
+            return invalid("diffusion", self.diffusion);
+        }
+        if !self.initial_speed.is_finite() || self.initial_speed < 0.0 {
+            return invalid("initial_speed", self.initial_speed);
+        }
+        self.radius.validate()
+    }
+}
+
+// What happens when particles of two species touch
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Interaction {
+    Count,  // A collision: counted, logged and resolved
+    Ignore, // The particles pass through each other unnoticed
+    React,  // A collision that also fires the rule's reaction
+}
+
+// What a reaction does to the two particles that collided
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Reaction {
+    // Replace both with one particle of species `into`, keeping their total
+    // mass, momentum and volume
+    Merge { into: String },
+    // Remove both
+    Annihilate,
+    // Keep both and add `count` new particles of species `product` where they met
+    Spawn { product: String, count: usize },
+}
+
+impl Reaction {
+    // Get the species the reaction creates, if any
+    pub fn product(&self) -> Option<&str> {
+        match self {
+            Reaction::Merge { into } => Some(into),
+            Reaction::Annihilate => None,
+            Reaction::Spawn { product, .. } => Some(product),
+        }
+    }
+}
+
+// One entry of the species-pair interaction matrix. Pairs without an entry
+// are counted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InteractionRule {
+    pub between: [String; 2], // Species names, in either order
+    pub interaction: Interaction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<Reaction>, // Required for, and only allowed with, react
+}
+
+impl InteractionRule {
+    // Check whether the rule covers a pair of species, given by name
+    pub fn covers(&self, first: &str, second: &str) -> bool {
+        let [a, b] = &self.between;
+        (a == first && b == second) || (a == second && b == first)
+    }
+}
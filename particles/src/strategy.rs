@@ -0,0 +1,891 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Barrier, Condvar, Mutex, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::system::{collision_grid, contact_share};
+use crate::{AtomicParticles, ConfigError, Contact, ParticleSystem, SimulationConfig};
+
+// The ways the simulators can share a ParticleSystem between threads
+// Only the strategies that check each step before the next move can resolve
+// collisions (SimulationConfig::restitution): single-lock, split, and rwlock
+// with alternate fairness. The others count collisions without a response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Strategy {
+    SingleLock,   // Each thread moves and checks collisions under one lock
+    Split,        // Separate move and collision threads take turns on each step
+    DoubleBuffer, // Collision threads check step N while the mover writes step N+1
+    LockFree,     // Movers and collision threads share atomic positions with no lock
+    RwLock,       // One mover writes while several collision readers share a read lock
+}
+
+impl Strategy {
+    pub const NAMES: &'static [&'static str] = &[
+        "single-lock",
+        "split",
+        "double-buffer",
+        "lock-free",
+        "rwlock",
+    ];
+
+    // Check whether the strategy moves with a single thread. Its moves can
+    // still be shared between move workers.
+    pub fn has_one_mover(&self) -> bool {
+        matches!(self, Strategy::DoubleBuffer | Strategy::RwLock)
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "single-lock" => Ok(Strategy::SingleLock),
+            "split" => Ok(Strategy::Split),
+            "double-buffer" => Ok(Strategy::DoubleBuffer),
+            "lock-free" => Ok(Strategy::LockFree),
+            "rwlock" => Ok(Strategy::RwLock),
+            _ => Err(ConfigError::InvalidValue {
+                key: "--strategy".to_string(),
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::SingleLock => write!(f, "single-lock"),
+            Strategy::Split => write!(f, "split"),
+            Strategy::DoubleBuffer => write!(f, "double-buffer"),
+            Strategy::LockFree => write!(f, "lock-free"),
+            Strategy::RwLock => write!(f, "rwlock"),
+        }
+    }
+}
+
+// How the rwlock strategy shares the lock between the mover and the readers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RwFairness {
+    Platform,       // Take the lock as it comes, leaving fairness to the platform's RwLock
+    WriterPriority, // Readers hold back while the mover is waiting, so it cannot starve
+    Alternate,      // Strict turns: one move, then every reader checks once
+}
+
+impl RwFairness {
+    pub const NAMES: &'static [&'static str] = &["platform", "writer-priority", "alternate"];
+}
+
+impl FromStr for RwFairness {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "platform" => Ok(RwFairness::Platform),
+            "writer-priority" => Ok(RwFairness::WriterPriority),
+            "alternate" => Ok(RwFairness::Alternate),
+            _ => Err(ConfigError::InvalidValue {
+                key: "--fairness".to_string(),
+                value: name.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for RwFairness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RwFairness::Platform => write!(f, "platform"),
+            RwFairness::WriterPriority => write!(f, "writer-priority"),
+            RwFairness::Alternate => write!(f, "alternate"),
+        }
+    }
+}
+
+// Threading options for a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOptions {
+    pub strategy: Strategy,
+    pub move_threads: usize, // Threads moving particles (all threads for single-lock)
+    pub collision_threads: usize, // Threads checking for collisions
+    pub fairness: RwFairness, // Lock sharing policy for the rwlock strategy
+}
+
+impl RunOptions {
+    pub fn new(strategy: Strategy) -> Self {
+        RunOptions {
+            strategy,
+            move_threads: 1,
+            collision_threads: 1,
+            fairness: RwFairness::Alternate,
+        }
+    }
+
+    // Check whether every step is checked before the next move, so the strategy
+    // can resolve the collisions it finds and fire reactions
+    pub fn resolves_collisions(&self) -> bool {
+        match self.strategy {
+            Strategy::SingleLock | Strategy::Split => true,
+            Strategy::RwLock => self.fairness == RwFairness::Alternate,
+            Strategy::DoubleBuffer | Strategy::LockFree => false,
+        }
+    }
+
+    // Check whether each step is recorded as soon as it has been checked, with
+    // the system holding the particles of that step, so frames can be taken
+    pub fn records_each_step(&self) -> bool {
+        self.resolves_collisions() || self.strategy == Strategy::DoubleBuffer
+    }
+
+    // Reject options that cannot run with the given config
+    pub fn check(&self, config: &SimulationConfig) -> Result<(), ConfigError> {
+        if self.move_threads > 1 && self.strategy.has_one_mover() {
+            return Err(ConfigError::Invalid(format!(
+                "{} has one move thread, not {}; use --move-workers to share its moves",
+                self.strategy, self.move_threads
+            )));
+        }
+        if config.restitution.is_some() && !self.resolves_collisions() {
+            return Err(ConfigError::Invalid(format!(
+                "restitution needs single-lock, split or rwlock with alternate fairness, \
+                 not {} with {} fairness",
+                self.strategy, self.fairness
+            )));
+        }
+        if config.has_reactions() && !self.resolves_collisions() {
+            return Err(ConfigError::Invalid(format!(
+                "reactions need single-lock, split or rwlock with alternate fairness, \
+                 not {} with {} fairness",
+                self.strategy, self.fairness
+            )));
+        }
+        Ok(())
+    }
+}
+
+// What a run produced, including how long threads were blocked waiting for
+// access to the particles. Writers are threads that move particles, readers
+// are threads that only check for collisions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunReport {
+    pub collisions: usize, // Pairs entering contact
+    pub overlaps: usize,   // Pairs in contact, summed over every step
+    pub species_collisions: BTreeMap<(String, String), usize>, // Collisions by species pair
+    pub population: Vec<(String, usize)>, // Particles of each species at the end
+    pub writer_wait: Duration,
+    pub writer_acquisitions: u64,
+    pub reader_wait: Duration,
+    pub reader_acquisitions: u64,
+}
+
+// Adds up the time threads spend blocked on one side of the lock
+#[derive(Default)]
+struct WaitTimer {
+    nanos: AtomicU64,
+    acquisitions: AtomicU64,
+}
+
+impl WaitTimer {
+    // Run `wait`, which blocks until access is granted, and record how long it took
+    fn time<T, F: FnOnce() -> T>(&self, wait: F) -> T {
+        let start_time = Instant::now();
+        let granted = wait();
+        self.nanos
+            .fetch_add(start_time.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        granted
+    }
+
+    fn total(&self) -> (Duration, u64) {
+        (
+            Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+            self.acquisitions.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl RunReport {
+    fn new(system: &ParticleSystem, writers: &WaitTimer, readers: &WaitTimer) -> Self {
+        let (writer_wait, writer_acquisitions) = writers.total();
+        let (reader_wait, reader_acquisitions) = readers.total();
+        RunReport {
+            collisions: system.get_collision_count(),
+            overlaps: system.get_overlap_count(),
+            species_collisions: system.get_species_collisions(),
+            population: system.population().final_counts(),
+            writer_wait,
+            writer_acquisitions,
+            reader_wait,
+            reader_acquisitions,
+        }
+    }
+}
+
+// Decides whether another step should start: either until the system has
+// completed the configured number of steps, counting any it had completed
+// before the run (e.g. when resumed from a snapshot), or, in wall-clock mode,
+// until the configured duration has passed
+#[derive(Clone)]
+struct Limit {
+    start_time: Instant,
+    duration: Option<Duration>,
+    first: u64, // Steps the system had completed before the run
+    steps: u64, // Steps to run
+}
+
+impl Limit {
+    fn new(system: &ParticleSystem) -> Self {
+        let config = system.config();
+        Limit {
+            start_time: Instant::now(),
+            duration: config.move_duration.map(|secs| Duration::new(secs, 0)),
+            first: system.step_count(),
+            steps: config.steps.saturating_sub(system.step_count()),
+        }
+    }
+
+    // Check whether the step after `completed` steps of this run should run
+    fn allows(&self, completed: u64) -> bool {
+        match self.duration {
+            Some(duration) => self.start_time.elapsed() < duration,
+            None => completed < self.steps,
+        }
+    }
+
+    // Check whether another step should run once the system has completed
+    // `step_count` steps in all
+    fn allows_step(&self, step_count: u64) -> bool {
+        self.allows(step_count - self.first)
+    }
+}
+
+// Run the chosen strategy to completion and report the total collisions counted
+pub fn run(system: &Arc<Mutex<ParticleSystem>>, options: &RunOptions) -> RunReport {
+    match options.strategy {
+        Strategy::SingleLock => run_single_lock(system, options.move_threads),
+        Strategy::Split => run_split(system, options.move_threads, options.collision_threads),
+        Strategy::DoubleBuffer => run_double_buffer(system, options.collision_threads),
+        Strategy::LockFree => {
+            run_lock_free(system, options.move_threads, options.collision_threads)
+        }
+        Strategy::RwLock => run_rwlock(system, options.collision_threads, options.fairness),
+    }
+}
+
+// Run the chosen strategy to completion like `run`, saving a snapshot to
+// `path` at the end and, if `every` is set, after every `every` steps.
+// Runs split into segments this way give the same results as one run with
+// the deterministic strategies. Wall-clock runs are only saved at the end.
+pub fn run_with_checkpoints(
+    system: &Arc<Mutex<ParticleSystem>>,
+    options: &RunOptions,
+    path: &Path,
+    every: Option<u64>,
+) -> io::Result<RunReport> {
+    let (end, timed) = {
+        let system = system.lock().unwrap();
+        (
+            system.config().steps,
+            system.config().move_duration.is_some(),
+        )
+    };
+    let mut report = RunReport::default();
+    loop {
+        let step_count = system.lock().unwrap().step_count();
+        let stop = match every {
+            Some(every) if !timed => (step_count + every).min(end),
+            _ => end,
+        };
+        system.lock().unwrap().set_steps(stop);
+        let segment = run(system, options);
+        let mut system = system.lock().unwrap();
+        system.set_steps(end);
+        system.snapshot().save(path)?;
+
+        report = RunReport {
+            writer_wait: report.writer_wait + segment.writer_wait,
+            writer_acquisitions: report.writer_acquisitions + segment.writer_acquisitions,
+            reader_wait: report.reader_wait + segment.reader_wait,
+            reader_acquisitions: report.reader_acquisitions + segment.reader_acquisitions,
+            ..segment
+        };
+        if timed || system.step_count() >= end {
+            return Ok(report);
+        }
+    }
+}
+
+// Each thread locks the system and runs a whole step under the lock.
+// Moves draw from the system's own random stream under the lock, so a seed gives
+// the same positions and totals whichever thread takes each step.
+fn run_single_lock(system: &Arc<Mutex<ParticleSystem>>, threads: usize) -> RunReport {
+    let limit = Limit::new(&system.lock().unwrap());
+    let writers = Arc::new(WaitTimer::default());
+
+    let handles = (0..threads)
+        .map(|_| {
+            let system = Arc::clone(system);
+            let limit = limit.clone();
+            let writers = Arc::clone(&writers);
+            thread::spawn(move || loop {
+                let mut system = writers.time(|| system.lock().unwrap());
+                if !limit.allows_step(system.step_count()) {
+                    break;
+                }
+                system.tick();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let report = RunReport::new(&system.lock().unwrap(), &writers, &WaitTimer::default());
+    report
+}
+
+// Hand-off between the move and collision threads in the split strategy
+struct Phase {
+    moved: bool, // The current step has been moved and is waiting to be checked
+    done: bool,  // The limit has been reached, or a thread has panicked
+}
+
+// Ends a split run when the thread holding it panics, so the others stop
+// waiting for a turn that will never come
+struct EndOnPanic<'a>(&'a (Mutex<Phase>, Condvar));
+
+impl Drop for EndOnPanic<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let (state, turn) = self.0;
+            state.lock().unwrap_or_else(PoisonError::into_inner).done = true;
+            turn.notify_all();
+        }
+    }
+}
+
+// Move threads and collision threads take turns on the lock.
+// Each step is moved exactly once and then checked exactly once, so both
+// sides run the same number of iterations. If a thread panics the others
+// stop, and the panic is passed on to the caller.
+fn run_split(
+    system: &Arc<Mutex<ParticleSystem>>,
+    move_threads: usize,
+    collision_threads: usize,
+) -> RunReport {
+    let limit = Limit::new(&system.lock().unwrap());
+    let writers = Arc::new(WaitTimer::default());
+    let readers = Arc::new(WaitTimer::default());
+    let phase = Arc::new((
+        Mutex::new(Phase {
+            moved: false,
+            done: false,
+        }),
+        Condvar::new(),
+    ));
+
+    let mut handles = Vec::new();
+    for _ in 0..move_threads {
+        let system = Arc::clone(system);
+        let limit = limit.clone();
+        let phase = Arc::clone(&phase);
+        let writers = Arc::clone(&writers);
+        handles.push(thread::spawn(move || {
+            let _end = EndOnPanic(&phase);
+            let (state, turn) = &*phase;
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                // Waiting for our turn counts as waiting for the lock
+                state = writers.time(|| {
+                    turn.wait_while(state, |state| state.moved && !state.done)
+                        .unwrap_or_else(PoisonError::into_inner)
+                });
+                if state.done {
+                    break;
+                }
+                // Move particles with exclusive lock
+                let mut system = system.lock().unwrap();
+                if limit.allows_step(system.step_count()) {
+                    system.move_particles();
+                    state.moved = true;
+                } else {
+                    state.done = true;
+                }
+                turn.notify_all();
+            }
+        }));
+    }
+    for _ in 0..collision_threads {
+        let system = Arc::clone(system);
+        let phase = Arc::clone(&phase);
+        let readers = Arc::clone(&readers);
+        handles.push(thread::spawn(move || {
+            let _end = EndOnPanic(&phase);
+            let (state, turn) = &*phase;
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                state = readers.time(|| {
+                    turn.wait_while(state, |state| !state.moved && !state.done)
+                        .unwrap_or_else(PoisonError::into_inner)
+                });
+                if state.done {
+                    break;
+                }
+                // Check for collisions with the particles
+                system.lock().unwrap().finish_step();
+                state.moved = false;
+                turn.notify_all();
+            }
+        }));
+    }
+
+    let mut panicked = None;
+    for handle in handles {
+        if let Err(payload) = handle.join() {
+            panicked.get_or_insert(payload);
+        }
+    }
+    if let Some(payload) = panicked {
+        std::panic::resume_unwind(payload);
+    }
+    let report = RunReport::new(&system.lock().unwrap(), &writers, &readers);
+    report
+}
+
+// Movement and collision checking run at the same time on two buffers.
+//
+// The calling thread becomes the mover and holds the system lock for the whole
+// run; the system's own particles are the back buffer. The front buffer holds
+// the last completed move and is only ever read while both phases run:
+//
+//   1. collision threads count the front buffer (step N)
+//   2. meanwhile the mover copies the front into the back and moves it (step N+1)
+//   3. everyone meets at a barrier, the mover swaps the buffers and records
+//      step N, then everyone meets at a second barrier and starts again
+//
+// Nothing is written while anyone reads it, so there are no races, and every
+// step is moved and checked exactly once, giving the same totals as a serial run.
+// Time spent at the barriers counts as waiting for the lock.
+fn run_double_buffer(system: &Arc<Mutex<ParticleSystem>>, collision_threads: usize) -> RunReport {
+    let mut system = system.lock().unwrap();
+    let limit = Limit::new(&system);
+    let config = system.config().clone();
+
+    let front = RwLock::new(system.particles().to_vec());
+    let tallies = (0..collision_threads)
+        .map(|_| Mutex::new(Vec::new()))
+        .collect::<Vec<_>>();
+    let pending = AtomicBool::new(false); // The front holds a step that still needs checking
+    let done = AtomicBool::new(false);
+    let barrier = Barrier::new(collision_threads + 1);
+    let mut moves = 0;
+    let writers = WaitTimer::default();
+    let readers = WaitTimer::default();
+
+    thread::scope(|scope| {
+        for (share, tally) in tallies.iter().enumerate() {
+            let (front, pending, done, barrier, config, readers) =
+                (&front, &pending, &done, &barrier, &config, &readers);
+            scope.spawn(move || loop {
+                if pending.load(Ordering::Acquire) {
+                    let particles = front.read().unwrap();
+                    let grid = collision_grid(&particles, config);
+                    let contacts =
+                        contact_share(&particles, config, grid.as_ref(), share, collision_threads);
+                    *tally.lock().unwrap() = contacts;
+                }
+                readers.time(|| {
+                    barrier.wait();
+                    barrier.wait();
+                });
+                if done.load(Ordering::Acquire) {
+                    break;
+                }
+            });
+        }
+
+        loop {
+            let moved = limit.allows(moves);
+            if moved {
+                system.copy_particles_from(&front.read().unwrap());
+                system.move_particles();
+                moves += 1;
+            }
+            writers.time(|| barrier.wait());
+
+            // Everyone else is waiting at the second barrier, so the buffers are ours.
+            // Swap first, so the system holds the step being recorded.
+            let checked = pending.load(Ordering::Acquire);
+            if moved || checked {
+                system.swap_particles(&mut front.write().unwrap());
+            }
+            if checked {
+                let contacts = tallies
+                    .iter()
+                    .flat_map(|tally| std::mem::take(&mut *tally.lock().unwrap()))
+                    .collect::<Vec<_>>();
+                system.record_step(&contacts);
+            }
+            pending.store(moved, Ordering::Release);
+            done.store(!moved, Ordering::Release);
+            writers.time(|| barrier.wait());
+            if !moved {
+                break;
+            }
+        }
+    });
+
+    // The last swap left the newest positions in the system
+    RunReport::new(&system, &writers, &readers)
+}
+
+// Movers and collision threads run over AtomicParticles with no lock.
+//
+// Each mover owns a contiguous chunk of particles and its own random stream,
+// taken from the system and handed back after the run, so the final positions
+// depend only on the seed and the mover count, and a later run carries on
+// the streams rather than repeating them. Every mover publishes how many
+// steps it has moved, and each collision thread makes one pass over its share
+// of the pairs per step, starting a pass only once every mover has finished
+// that step. Movers in turn wait for every collision
+// thread to start checking a step before they move past it, so the two sides
+// stay within a step of each other. A pass can still read some particles
+// after their next move, so the collision total changes from run to
+// run but stays close to the serial one. This is the trade-off against the
+// locking and double-buffered strategies.
+fn run_lock_free(
+    system: &Arc<Mutex<ParticleSystem>>,
+    move_threads: usize,
+    collision_threads: usize,
+) -> RunReport {
+    let mut system = system.lock().unwrap();
+    let limit = Limit::new(&system);
+    let config = system.config().clone();
+    let store = AtomicParticles::new(system.particles(), config.dimensions);
+    let chunk_size = store.len().div_ceil(move_threads).max(1);
+    let rngs = system.take_mover_rngs(move_threads);
+
+    // Steps each mover has moved, and passes each collision thread has started
+    let moved_steps = (0..move_threads)
+        .map(|_| AtomicU64::new(0))
+        .collect::<Vec<_>>();
+    let started = (0..collision_threads)
+        .map(|_| AtomicU64::new(0))
+        .collect::<Vec<_>>();
+    let movers_done = AtomicU64::new(0);
+    let least = |counters: &[AtomicU64]| {
+        counters
+            .iter()
+            .map(|counter| counter.load(Ordering::Acquire))
+            .min()
+            .unwrap_or(u64::MAX)
+    };
+
+    let (moved, passes, rngs) = thread::scope(|scope| {
+        // Each mover keeps the full state of its own chunk and only shares positions
+        // Every worker moves, even with no particles of its own, so each step is published
+        let particles = system.particles();
+        let movers = rngs
+            .into_iter()
+            .enumerate()
+            .map(|(worker, mut rng)| {
+                let chunk = particles
+                    .get(worker * chunk_size..)
+                    .map_or(&[][..], |rest| &rest[..rest.len().min(chunk_size)]);
+                let (store, limit, config) = (&store, &limit, &config);
+                let (moved_steps, started, movers_done) = (&moved_steps, &started, &movers_done);
+                let start = worker * chunk_size;
+                let mut chunk = chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, particle)| (start + i, *particle))
+                    .collect::<Vec<_>>();
+                scope.spawn(move || {
+                    let mut moves = 0;
+                    while limit.allows(moves) {
+                        // Wait for the slowest collision thread to start on the last
+                        // step, unless another mover has stopped and it never will
+                        while least(started) < moves && movers_done.load(Ordering::Acquire) == 0 {
+                            thread::yield_now();
+                        }
+                        chunk.retain_mut(|(i, particle)| {
+                            particle.move_particle(config, &mut rng);
+                            // Particles that cross an absorbing wall leave the system
+                            let inside = particle.is_inside(config);
+                            if inside {
+                                store.store(*i, particle);
+                            } else {
+                                store.remove(*i);
+                            }
+                            inside
+                        });
+                        moves += 1;
+                        moved_steps[worker].store(moves, Ordering::Release);
+                    }
+                    movers_done.fetch_add(1, Ordering::AcqRel);
+                    (chunk.into_iter().map(|(_, particle)| particle), rng)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let checkers = (0..collision_threads)
+            .map(|share| {
+                let (store, config) = (&store, &config);
+                let (moved_steps, started, movers_done) = (&moved_steps, &started, &movers_done);
+                let movers = moved_steps.len() as u64;
+                scope.spawn(move || {
+                    let mut passes = Vec::new();
+                    loop {
+                        // Wait until every mover has finished the step this pass checks
+                        let step = passes.len() as u64 + 1;
+                        while least(moved_steps) < step
+                            && movers_done.load(Ordering::Acquire) < movers
+                        {
+                            thread::yield_now();
+                        }
+                        if least(moved_steps) < step {
+                            break;
+                        }
+                        started[share].store(step, Ordering::Release);
+                        let particles = store.snapshot();
+                        let grid = collision_grid(&particles, config);
+                        passes.push(contact_share(
+                            &particles,
+                            config,
+                            grid.as_ref(),
+                            share,
+                            collision_threads,
+                        ));
+                    }
+                    passes
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut moved = Vec::new();
+        let mut rngs = Vec::new();
+        for mover in movers {
+            let (chunk, rng) = mover.join().unwrap();
+            moved.extend(chunk);
+            rngs.push(rng);
+        }
+        let passes = merge_passes(checkers.into_iter().map(|checker| checker.join().unwrap()));
+        (moved, passes, rngs)
+    });
+
+    system.copy_particles_from(&moved);
+    system.put_mover_rngs(rngs);
+    for contacts in passes {
+        system.record_step(&contacts);
+    }
+    let no_waits = WaitTimer::default();
+    RunReport::new(&system, &no_waits, &no_waits)
+}
+
+// A pass's contacts: the pairs in contact that one collision thread found in its share
+type Contacts = Vec<Contact>;
+
+// Join up each step's shares from every collision thread
+fn merge_passes<I: IntoIterator<Item = Vec<Contacts>>>(checkers: I) -> Vec<Contacts> {
+    let mut passes: Vec<Contacts> = Vec::new();
+    for checker in checkers {
+        for (step, contacts) in checker.into_iter().enumerate() {
+            match passes.get_mut(step) {
+                Some(total) => total.extend(contacts),
+                None => passes.push(contacts),
+            }
+        }
+    }
+    passes
+}
+
+// One mover takes the write lock to move the particles while `readers`
+// collision threads share the read lock, each counting its share of the pairs.
+//
+// Each reader makes one pass per step, waiting for the mover to finish it.
+// With Platform or WriterPriority fairness the mover only waits for every
+// reader to take the read lock for a step before it asks for the write lock to
+// move the next one, so the two sides contend for the lock on every step and
+// the fairness policy decides who waits. With Alternate fairness every step is
+// moved once and then checked once by all readers under strict turns, and
+// collisions can be resolved between them. Either way each pass checks exactly
+// one step, giving the serial totals.
+fn run_rwlock(
+    system: &Arc<Mutex<ParticleSystem>>,
+    readers: usize,
+    fairness: RwFairness,
+) -> RunReport {
+    let mut system = system.lock().unwrap();
+    let limit = Limit::new(&system);
+    let lock = RwLock::new(&mut *system);
+    let writer_timer = WaitTimer::default();
+    let reader_timer = WaitTimer::default();
+
+    match fairness {
+        RwFairness::Alternate => {
+            rwlock_alternate(&lock, &limit, readers, &writer_timer, &reader_timer)
+        }
+        _ => {
+            let passes = rwlock_free(
+                &lock,
+                &limit,
+                readers,
+                fairness,
+                &writer_timer,
+                &reader_timer,
+            );
+            let mut system = lock.write().unwrap();
+            for contacts in passes {
+                system.record_step(&contacts);
+            }
+        }
+    }
+
+    let system = lock.into_inner().unwrap();
+    RunReport::new(system, &writer_timer, &reader_timer)
+}
+
+// Reader/writer loop contending for the lock; returns the contacts of each reader pass
+fn rwlock_free(
+    lock: &RwLock<&mut ParticleSystem>,
+    limit: &Limit,
+    readers: usize,
+    fairness: RwFairness,
+    writer_timer: &WaitTimer,
+    reader_timer: &WaitTimer,
+) -> Vec<Contacts> {
+    let writer_waiting = AtomicBool::new(false);
+    let moved = AtomicU64::new(0); // Steps the mover has finished
+    let mover_done = AtomicBool::new(false);
+    let started = (0..readers).map(|_| AtomicU64::new(0)).collect::<Vec<_>>(); // Passes each reader has taken the read lock for
+
+    thread::scope(|scope| {
+        let checkers = (0..readers)
+            .map(|share| {
+                let (writer_waiting, moved, mover_done, started) =
+                    (&writer_waiting, &moved, &mover_done, &started);
+                scope.spawn(move || {
+                    let mut passes = Vec::new();
+                    loop {
+                        // Wait for the mover to finish the step this pass checks
+                        let step = passes.len() as u64 + 1;
+                        while moved.load(Ordering::Acquire) < step
+                            && !mover_done.load(Ordering::Acquire)
+                        {
+                            thread::yield_now();
+                        }
+                        if moved.load(Ordering::Acquire) < step {
+                            break;
+                        }
+                        let system = reader_timer.time(|| {
+                            // Let a waiting mover go first
+                            while fairness == RwFairness::WriterPriority
+                                && writer_waiting.load(Ordering::Acquire)
+                            {
+                                thread::yield_now();
+                            }
+                            lock.read().unwrap()
+                        });
+                        started[share].store(step, Ordering::Release);
+                        let particles = system.particles();
+                        let grid = collision_grid(particles, system.config());
+                        passes.push(contact_share(
+                            particles,
+                            system.config(),
+                            grid.as_ref(),
+                            share,
+                            readers,
+                        ));
+                    }
+                    passes
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut moves = 0;
+        while limit.allows(moves) {
+            // Every reader holds the read lock for the last step, or has
+            // checked it, before the mover asks to change it
+            while started
+                .iter()
+                .any(|started| started.load(Ordering::Acquire) < moves)
+            {
+                thread::yield_now();
+            }
+            writer_waiting.store(true, Ordering::Release);
+            let mut system = writer_timer.time(|| lock.write().unwrap());
+            writer_waiting.store(false, Ordering::Release);
+            system.move_particles();
+            moves += 1;
+            moved.store(moves, Ordering::Release);
+        }
+        mover_done.store(true, Ordering::Release);
+
+        merge_passes(checkers.into_iter().map(|checker| checker.join().unwrap()))
+    })
+}
+
+// Strict turns between the mover and the readers. The mover resolves and
+// records each step once the readers have checked it, before moving again.
+fn rwlock_alternate(
+    lock: &RwLock<&mut ParticleSystem>,
+    limit: &Limit,
+    readers: usize,
+    writer_timer: &WaitTimer,
+    reader_timer: &WaitTimer,
+) {
+    let tallies = (0..readers)
+        .map(|_| Mutex::new(Vec::new()))
+        .collect::<Vec<_>>();
+    let done = AtomicBool::new(false);
+    let barrier = Barrier::new(readers + 1);
+
+    thread::scope(|scope| {
+        for (share, tally) in tallies.iter().enumerate() {
+            let (done, barrier) = (&done, &barrier);
+            scope.spawn(move || loop {
+                reader_timer.time(|| barrier.wait());
+                if done.load(Ordering::Acquire) {
+                    break;
+                }
+                let system = lock.read().unwrap();
+                let particles = system.particles();
+                let grid = collision_grid(particles, system.config());
+                let contacts =
+                    contact_share(particles, system.config(), grid.as_ref(), share, readers);
+                *tally.lock().unwrap() = contacts;
+                drop(system);
+                barrier.wait();
+            });
+        }
+
+        let mut moves = 0;
+        loop {
+            let moved = limit.allows(moves);
+            if moved {
+                lock.write().unwrap().move_particles();
+                moves += 1;
+            }
+            done.store(!moved, Ordering::Release);
+            barrier.wait();
+            if !moved {
+                break;
+            }
+            // The readers are checking; wait for them to finish
+            writer_timer.time(|| barrier.wait());
+            let contacts = tallies
+                .iter()
+                .flat_map(|tally| std::mem::take(&mut *tally.lock().unwrap()))
+                .collect::<Vec<_>>();
+            let mut system = lock.write().unwrap();
+            system.respond(&contacts);
+            system.record_step(&contacts);
+        }
+    })
+}
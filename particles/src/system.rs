@@ -0,0 +1,834 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use scoped_threadpool::Pool;
+
+use crate::{
+    stream_rng, Boundary, CollisionEvent, CollisionMethod, ConfigError, Contact, Frame, Particle,
+    Population, Reaction, SimRng, SimulationConfig, Snapshot, SnapshotError, SpatialGrid, Species,
+    SNAPSHOT_VERSION,
+};
+
+// A callback that is handed every collision event
+type EventSink = Box<dyn FnMut(&CollisionEvent) + Send>;
+
+// A callback that is handed a frame every so many steps
+type FrameSink = Box<dyn FnMut(Frame) + Send>;
+
+// Define the ParticleSystem struct
+pub struct ParticleSystem {
+    particles: Vec<Particle>, // Kept in order of id
+    next_id: u64,             // Id for the next particle created; ids are never reused
+    config: SimulationConfig,
+    seed: u64,       // Seed every random stream in this system is derived from
+    rng: SimRng,     // Stream used for placing and moving particles
+    step_count: u64, // Number of completed steps
+    collision_count: Arc<AtomicUsize>, // Atomic counter for collisions (pairs entering contact)
+    overlap_count: Arc<AtomicUsize>, // Pairs in contact, summed over every step
+    contacts: HashSet<(u64, u64)>, // Ids of pairs in contact at the last completed step
+    spawned: Vec<(u64, u64)>, // Pairs made touching by this step's spawns, added to `contacts`
+    species_collisions: BTreeMap<(usize, usize), usize>, // Collisions by species pair, smaller index first
+    population: Population,                              // Particles of each species, step by step
+    event_sink: Option<Mutex<EventSink>>,                // Receives each collision event, if set
+    frame_sink: Option<(u64, Mutex<FrameSink>)>, // Receives a frame every so many steps, if set
+    collision_pool: Option<Mutex<Pool>>,         // Workers for check_collisions, if more than one
+    move_pool: Option<Mutex<Pool>>,              // Workers for move_particles, if more than one
+    move_rngs: Vec<SimRng>,                      // One random stream per move worker
+}
+
+impl ParticleSystem {
+    // Create a new ParticleSystem with the particles of each species given in the config.
+    // Uses the configured seed, or picks one at random so the run can still be replayed.
+    // Panics if the particles cannot be laid out; try_new reports that instead.
+    pub fn new(config: SimulationConfig) -> Self {
+        Self::try_new(config).unwrap_or_else(|err| panic!("cannot lay out particles: {}", err))
+    }
+
+    // Create a new ParticleSystem, or say why its particles cannot be laid out,
+    // say because a layout file is missing or the enclosure is too crowded
+    pub fn try_new(config: SimulationConfig) -> Result<Self, ConfigError> {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        Self::try_with_rng(config, seed, stream_rng(seed, 0))
+    }
+
+    // Create a new ParticleSystem that draws from the given generator.
+    // `seed` is used to derive the per-worker streams.
+    // Panics if the particles cannot be laid out; try_with_rng reports that instead.
+    pub fn with_rng(config: SimulationConfig, seed: u64, rng: SimRng) -> Self {
+        Self::try_with_rng(config, seed, rng)
+            .unwrap_or_else(|err| panic!("cannot lay out particles: {}", err))
+    }
+
+    // Create a new ParticleSystem that draws from the given generator, or say
+    // why its particles cannot be laid out
+    pub fn try_with_rng(
+        config: SimulationConfig,
+        seed: u64,
+        mut rng: SimRng,
+    ) -> Result<Self, ConfigError> {
+        // Species are created one after another, so ids run through each in turn
+        let mut particles = Vec::new();
+        for (index, species) in config.species_list().iter().enumerate() {
+            for _ in 0..species.count {
+                let id = particles.len() as u64;
+                particles.push(Particle::of_species(id, index, species, &config, &mut rng));
+            }
+        }
+        config.layout.place(&mut particles, &config, &mut rng)?;
+        let mut system = Self::empty(config, seed, rng);
+        system.next_id = particles.len() as u64;
+        system.population.record(0, &particles);
+        system.particles = particles;
+        Ok(system)
+    }
+
+    // Restore a system from a snapshot. Stepping it gives exactly what the
+    // system the snapshot was taken from would have given, provided the
+    // number of move workers is the same.
+    pub fn from_snapshot(snapshot: Snapshot) -> Result<Self, SnapshotError> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version(snapshot.version));
+        }
+        let config = snapshot.config;
+        config
+            .validate()
+            .map_err(|err| SnapshotError::Invalid(err.to_string()))?;
+        let species = config.species_list().len();
+        if snapshot.population.species().len() != species
+            || snapshot
+                .particles
+                .iter()
+                .any(|particle| particle.species() >= species)
+        {
+            return Err(SnapshotError::Invalid(
+                "a particle belongs to an unknown species".to_string(),
+            ));
+        }
+        if snapshot
+            .particles
+            .windows(2)
+            .any(|pair| pair[0].id() >= pair[1].id())
+        {
+            return Err(SnapshotError::Invalid(
+                "particles are not in order of id".to_string(),
+            ));
+        }
+
+        let mut system = Self::empty(config, snapshot.seed, snapshot.rng);
+        // Keep the saved worker streams unless the worker count has changed.
+        // New ones are drawn from the saved system stream rather than the
+        // seed, so they do not repeat the draws of the start of the run.
+        if snapshot.move_rngs.len() == system.move_rngs.len() {
+            system.move_rngs = snapshot.move_rngs;
+        } else if !system.move_rngs.is_empty() {
+            system.move_rngs = system.draw_rngs(system.move_rngs.len());
+        }
+        system.particles = snapshot.particles;
+        system.next_id = snapshot.next_id;
+        system.step_count = snapshot.step_count;
+        system.collision_count = Arc::new(AtomicUsize::new(snapshot.collision_count));
+        system.overlap_count = Arc::new(AtomicUsize::new(snapshot.overlap_count));
+        system.contacts = snapshot.contacts.into_iter().collect();
+        system.species_collisions = snapshot.species_collisions.into_iter().collect();
+        system.population = snapshot.population;
+        Ok(system)
+    }
+
+    // Create a system holding the particles of a recorded frame, to replay
+    // a trajectory through. Species indices must refer to the config's species.
+    pub fn from_frame(config: SimulationConfig, frame: Frame) -> Self {
+        let seed = config.seed.unwrap_or(0);
+        let mut system = Self::empty(config, seed, stream_rng(seed, 0));
+        system.next_id = frame
+            .particles
+            .iter()
+            .map(|p| p.id() + 1)
+            .max()
+            .unwrap_or(0);
+        system.step_count = frame.step;
+        system.population.record(frame.step, &frame.particles);
+        system.particles = frame.particles;
+        system
+    }
+
+    // Record a later frame of a replayed trajectory as if the system had
+    // stepped to it, finding contacts with this system's config. Contacts
+    // made and broken between recorded frames are not seen.
+    // Returns the collisions in this frame.
+    pub fn replay_frame(&mut self, frame: Frame) -> usize {
+        self.particles = frame.particles;
+        self.step_count = frame.step.saturating_sub(1);
+        let contacts = self.find_contacts();
+        self.record_step(&contacts)
+    }
+
+    // Create a system with no particles, and the worker pools and streams
+    // the config asks for
+    fn empty(config: SimulationConfig, seed: u64, rng: SimRng) -> Self {
+        let collision_pool = match config.collision_workers {
+            0 | 1 => None,
+            workers => Some(Mutex::new(Pool::new(workers as u32))),
+        };
+        let (move_pool, move_rngs) = match config.move_workers {
+            0 | 1 => (None, Vec::new()),
+            workers => (
+                Some(Mutex::new(Pool::new(workers as u32))),
+                (0..workers)
+                    .map(|w| stream_rng(seed, w as u64 + 1))
+                    .collect(),
+            ),
+        };
+        ParticleSystem {
+            particles: Vec::new(),
+            next_id: 0,
+            population: Population::new(&config),
+            config,
+            seed,
+            rng,
+            step_count: 0,
+            collision_count: Arc::new(AtomicUsize::new(0)), // Initialize the atomic counter
+            overlap_count: Arc::new(AtomicUsize::new(0)),
+            contacts: HashSet::new(),
+            spawned: Vec::new(),
+            species_collisions: BTreeMap::new(),
+            event_sink: None,
+            frame_sink: None,
+            collision_pool,
+            move_pool,
+            move_rngs,
+        }
+    }
+
+    // Capture the full state of the system
+    pub fn snapshot(&self) -> Snapshot {
+        let mut contacts = self.contacts.iter().copied().collect::<Vec<_>>();
+        contacts.sort_unstable();
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            config: self.config.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            move_rngs: self.move_rngs.clone(),
+            step_count: self.step_count,
+            collision_count: self.get_collision_count(),
+            overlap_count: self.get_overlap_count(),
+            next_id: self.next_id,
+            particles: self.particles.clone(),
+            contacts,
+            species_collisions: self
+                .species_collisions
+                .iter()
+                .map(|(&pair, &count)| (pair, count))
+                .collect(),
+            population: self.population.without_history(),
+        }
+    }
+
+    // Move all particles within the system.
+    // With several move workers the particles are split into one contiguous
+    // chunk per worker, and each worker draws from its own random stream, so a
+    // seed and worker count always give the same positions.
+    pub fn move_particles(&mut self) {
+        let config = &self.config;
+        match &mut self.move_pool {
+            Some(pool) => {
+                let pool = pool.get_mut().unwrap();
+                let rngs = &mut self.move_rngs;
+                let chunk_size = self.particles.len().div_ceil(rngs.len()).max(1);
+                let chunks = self.particles.chunks_mut(chunk_size);
+                pool.scoped(|scope| {
+                    for (chunk, rng) in chunks.zip(rngs.iter_mut()) {
+                        scope.execute(move || {
+                            for particle in chunk {
+                                particle.move_particle(config, rng);
+                            }
+                        });
+                    }
+                });
+            }
+            None => {
+                for particle in &mut self.particles {
+                    particle.move_particle(config, &mut self.rng);
+                }
+            }
+        }
+        // Particles that crossed an absorbing wall leave the system
+        if config.boundary == Boundary::Absorbing {
+            self.particles.retain(|particle| particle.is_inside(config));
+        }
+    }
+
+    // Finish the current step: resolve and count its collisions and advance the
+    // step counter. Call after move_particles when the two phases run on different threads.
+    // The step's frame, if one is due, is taken after the response, so it
+    // shows resolved pairs pushed apart and reactions already fired.
+    pub fn finish_step(&mut self) -> usize {
+        let contacts = self.find_contacts();
+        self.respond(&contacts);
+        self.record_step(&contacts)
+    }
+
+    // Respond to the pairs in contact: resolve them if the config asks for a
+    // collision response, then fire the reactions of pairs that have just come
+    // into contact. Pairs are handled one after another in order of particle
+    // id, so a particle touching several others gets the same result on every run.
+    // The contacts must have been found on the particles as they are now.
+    pub fn respond(&mut self, contacts: &[Contact]) {
+        let mut contacts = contacts.to_vec();
+        contacts.sort_unstable_by_key(|contact| (contact.a, contact.b));
+        if let Some(restitution) = self.config.restitution {
+            self.resolve(&contacts, restitution);
+        }
+        if self.config.has_reactions() {
+            self.react(&contacts);
+        }
+    }
+
+    // Resolve each pair in contact, in order
+    fn resolve(&mut self, contacts: &[Contact], restitution: f32) {
+        for &Contact { a, b, .. } in contacts {
+            if let (Some(i), Some(j)) = (self.index_of(a), self.index_of(b)) {
+                // i < j since ids are in order
+                let (head, tail) = self.particles.split_at_mut(j);
+                head[i].resolve_collision(&mut tail[0], &self.config, restitution);
+            }
+        }
+    }
+
+    // Fire the reactions of pairs entering contact, in order. A particle takes
+    // part in at most one reaction per step; later pairs it belongs to are
+    // left alone. Removed particles are dropped and new ones added once every
+    // pair has been handled, keeping the particles in order of id.
+    // Spawned particles start on top of their parents, so those pairs, and the
+    // pairs of siblings, count as already in contact rather than colliding at
+    // the next step.
+    fn react(&mut self, contacts: &[Contact]) {
+        let mut reacted = HashSet::new();
+        let mut consumed = HashSet::new();
+        let mut created = Vec::new();
+        for contact in contacts {
+            let (a, b) = (contact.a, contact.b);
+            if self.contacts.contains(&(a, b)) || reacted.contains(&a) || reacted.contains(&b) {
+                continue;
+            }
+            let reaction = match self.config.reaction(contact.species.0, contact.species.1) {
+                Some(reaction) => reaction.clone(),
+                None => continue,
+            };
+            let (i, j) = match (self.index_of(a), self.index_of(b)) {
+                (Some(i), Some(j)) => (i, j),
+                _ => continue,
+            };
+            match reaction {
+                Reaction::Merge { into } => {
+                    let species = self.config.species_index(&into).unwrap();
+                    let id = self.new_id();
+                    let (first, second) = (&self.particles[i], &self.particles[j]);
+                    created.push(first.merge(second, id, species, &self.config));
+                    consumed.extend([a, b]);
+                }
+                Reaction::Annihilate => consumed.extend([a, b]),
+                Reaction::Spawn { product, count } => {
+                    let index = self.config.species_index(&product).unwrap();
+                    let species = self.config.species_list().swap_remove(index);
+                    let mut family = vec![a, b];
+                    for _ in 0..count {
+                        let id = self.new_id();
+                        let mut particle =
+                            Particle::of_species(id, index, &species, &self.config, &mut self.rng);
+                        particle.set_position(contact.midpoint);
+                        created.push(particle);
+                        self.spawned
+                            .extend(family.iter().map(|&relative| (relative, id)));
+                        family.push(id);
+                    }
+                }
+            }
+            reacted.extend([a, b]);
+        }
+        if !consumed.is_empty() {
+            self.particles
+                .retain(|particle| !consumed.contains(&particle.id()));
+        }
+        self.particles.extend(created);
+    }
+
+    // Take the next unused particle id
+    fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    // Find a particle by id. Particles are kept in order of id.
+    fn index_of(&self, id: u64) -> Option<usize> {
+        self.particles
+            .binary_search_by_key(&id, |particle| particle.id())
+            .ok()
+    }
+
+    // Record a step from the pairs in contact during it, which may have been
+    // found outside the system, e.g. by workers reading a copy of the particles.
+    // A collision is a pair entering contact: a pair that stays in contact over
+    // several steps counts once, but adds to the overlap count on every step.
+    // Each collision is passed to the event sink, in order of particle id.
+    // Returns the collisions in this step.
+    pub fn record_step(&mut self, contacts: &[Contact]) -> usize {
+        self.step_count += 1;
+        let mut entered = contacts
+            .iter()
+            .filter(|contact| !self.contacts.contains(&(contact.a, contact.b)))
+            .collect::<Vec<_>>();
+        if let Some(sink) = &mut self.event_sink {
+            let sink = sink.get_mut().unwrap();
+            entered.sort_by_key(|contact| (contact.a, contact.b));
+            for contact in &entered {
+                sink(&CollisionEvent::new(self.step_count, contact));
+            }
+        }
+
+        for contact in &entered {
+            let (a, b) = contact.species;
+            *self
+                .species_collisions
+                .entry((a.min(b), a.max(b)))
+                .or_insert(0) += 1;
+        }
+
+        let collisions = entered.len();
+        self.collision_count.fetch_add(collisions, Ordering::SeqCst);
+        self.overlap_count
+            .fetch_add(contacts.len(), Ordering::SeqCst);
+        self.contacts = contacts
+            .iter()
+            .map(|contact| (contact.a, contact.b))
+            .chain(self.spawned.drain(..))
+            .collect();
+        self.population.record(self.step_count, &self.particles);
+        if let Some((every, sink)) = &mut self.frame_sink {
+            if self.step_count.is_multiple_of(*every) {
+                let frame = Frame {
+                    step: self.step_count,
+                    particles: self.particles.clone(),
+                };
+                (sink.get_mut().unwrap())(frame);
+            }
+        }
+        collisions
+    }
+
+    // Hand every future collision event to `callback`
+    pub fn on_collision<F: FnMut(&CollisionEvent) + Send + 'static>(&mut self, callback: F) {
+        self.event_sink = Some(Mutex::new(Box::new(callback)));
+    }
+
+    // Send every future collision event down a channel. The channel closes
+    // when the events are stopped or the system is dropped.
+    pub fn collision_events(&mut self) -> Receiver<CollisionEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.on_collision(move |event| {
+            // The receiver may have hung up; the simulation carries on regardless
+            let _ = sender.send(*event);
+        });
+        receiver
+    }
+
+    // Stop handing out collision events
+    pub fn stop_collision_events(&mut self) {
+        self.event_sink = None;
+    }
+
+    // Hand the current frame, and then the frame after every `every`th step,
+    // to `callback`. Frames are taken when each step is recorded, so strategies
+    // that only record after the run (lock-free and free-running rwlock) give
+    // meaningless frames. Recording comes after the collision response, so
+    // with restitution or reactions a frame no longer holds every contact its
+    // step counted, and replaying the frames counts fewer collisions.
+    pub fn on_frame<F: FnMut(Frame) + Send + 'static>(&mut self, every: u64, mut callback: F) {
+        callback(self.frame());
+        self.frame_sink = Some((every.max(1), Mutex::new(Box::new(callback))));
+    }
+
+    // Send frames down a channel, as for on_frame. The channel closes when
+    // the frames are stopped or the system is dropped.
+    pub fn frames(&mut self, every: u64) -> Receiver<Frame> {
+        let (sender, receiver) = mpsc::channel();
+        self.on_frame(every, move |frame| {
+            // The receiver may have hung up; the simulation carries on regardless
+            let _ = sender.send(frame);
+        });
+        receiver
+    }
+
+    // Stop handing out frames
+    pub fn stop_frames(&mut self) {
+        self.frame_sink = None;
+    }
+
+    // Get every particle as they are now
+    pub fn frame(&self) -> Frame {
+        Frame {
+            step: self.step_count,
+            particles: self.particles.clone(),
+        }
+    }
+
+    // Run one step: move every particle once, then check collisions once
+    pub fn tick(&mut self) -> usize {
+        self.move_particles();
+        self.finish_step()
+    }
+
+    // Run `steps` steps and return the collisions counted during them
+    pub fn step(&mut self, steps: u64) -> usize {
+        (0..steps).map(|_| self.tick()).sum()
+    }
+
+    // Run whole steps until `duration` has passed and return the collisions counted
+    pub fn run_for(&mut self, duration: Duration) -> usize {
+        let start_time = Instant::now();
+        let mut collisions = 0;
+        while start_time.elapsed() < duration {
+            collisions += self.tick();
+        }
+        collisions
+    }
+
+    // Get the number of completed steps
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+
+    // Get the seed this system's random streams are derived from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Get an independent random stream for a worker thread.
+    // The same seed and worker index always give the same stream.
+    pub fn worker_rng(&self, worker: usize) -> SimRng {
+        stream_rng(self.seed, worker as u64 + 1)
+    }
+
+    // Take the random streams for `movers` threads that each move a
+    // contiguous chunk of the particles, as move_particles does: the system's
+    // own stream for a single mover, otherwise the move workers' streams,
+    // drawing new ones from the system's stream if there are not `movers` of
+    // them. Hand them back with put_mover_rngs once the moves are done, so
+    // the next run carries on from them instead of repeating their draws.
+    pub(crate) fn take_mover_rngs(&mut self, movers: usize) -> Vec<SimRng> {
+        if movers == 1 {
+            return vec![self.rng.clone()];
+        }
+        if self.move_rngs.len() != movers {
+            self.move_rngs = self.draw_rngs(movers);
+        }
+        std::mem::take(&mut self.move_rngs)
+    }
+
+    // Keep the streams taken with take_mover_rngs, as they are after the moves
+    pub(crate) fn put_mover_rngs(&mut self, mut rngs: Vec<SimRng>) {
+        match rngs.len() {
+            1 => self.rng = rngs.pop().unwrap(),
+            _ => self.move_rngs = rngs,
+        }
+    }
+
+    // Seed `count` new streams from the system's own stream
+    fn draw_rngs(&mut self, count: usize) -> Vec<SimRng> {
+        (0..count)
+            .map(|_| SimRng::from_rng(&mut self.rng))
+            .collect()
+    }
+
+    // Get the configuration this system was created with
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    // Add a species to the config, say for a replay that meets a species the
+    // config does not list, and return its index. A species already listed
+    // keeps its own settings and index.
+    pub fn add_species(&mut self, species: Species) -> usize {
+        if let Some(index) = self.config.species_index(&species.name) {
+            return index;
+        }
+        // Without a list the settings make one species; it keeps index 0
+        self.config.species = self.config.species_list();
+        self.population.add_species(&species.name);
+        self.config.species.push(species);
+        self.config.species.len() - 1
+    }
+
+    // Change the number of completed steps at which runs stop
+    pub fn set_steps(&mut self, steps: u64) {
+        self.config.steps = steps;
+    }
+
+    // Get the number of particles
+    pub fn get_particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    // Get the particles themselves
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    // Swap the system's particles with another buffer of particles
+    pub fn swap_particles(&mut self, buffer: &mut Vec<Particle>) {
+        std::mem::swap(&mut self.particles, buffer);
+    }
+
+    // Overwrite the particles with a copy of `source`
+    pub fn copy_particles_from(&mut self, source: &[Particle]) {
+        self.particles.clear();
+        self.particles.extend_from_slice(source);
+    }
+
+    // Get all particle positions for testing
+    pub fn get_particle_positions(&self) -> Vec<(f32, f32)> {
+        self.particles.iter().map(|p| p.get_position()).collect()
+    }
+
+    // Function to check for collisions between particles, using the broad phase
+    // chosen in the config and the collision worker pool if there is one.
+    // Returns the number of pairs in contact right now.
+    pub fn check_collisions(&self) -> usize {
+        match &self.collision_pool {
+            Some(pool) => self.check_collisions_in(&mut pool.lock().unwrap()),
+            None => {
+                let grid = collision_grid(&self.particles, &self.config);
+                count_collision_share(&self.particles, &self.config, grid.as_ref(), 0, 1)
+            }
+        }
+    }
+
+    // Check for collisions on the threads of `pool`. Each worker keeps its own
+    // tally over its share of the pairs, and the tallies are summed at the end,
+    // so the result matches the serial check exactly.
+    pub fn check_collisions_in(&self, pool: &mut Pool) -> usize {
+        let grid = collision_grid(&self.particles, &self.config);
+        let grid = grid.as_ref();
+        self.share_out(pool, |share, shares| {
+            count_collision_share(&self.particles, &self.config, grid, share, shares)
+        })
+        .iter()
+        .sum()
+    }
+
+    // Find every pair of particles in contact right now
+    pub fn find_contacts(&self) -> Vec<Contact> {
+        let grid = collision_grid(&self.particles, &self.config);
+        let grid = grid.as_ref();
+        match &self.collision_pool {
+            Some(pool) => self
+                .share_out(&mut pool.lock().unwrap(), |share, shares| {
+                    contact_share(&self.particles, &self.config, grid, share, shares)
+                })
+                .concat(),
+            None => contact_share(&self.particles, &self.config, grid, 0, 1),
+        }
+    }
+
+    // Run `work(share, shares)` for every share on the threads of `pool`
+    fn share_out<T, F>(&self, pool: &mut Pool, work: F) -> Vec<T>
+    where
+        T: Default + Send,
+        F: Fn(usize, usize) -> T + Sync,
+    {
+        let shares = pool.thread_count() as usize;
+        let mut results = (0..shares).map(|_| T::default()).collect::<Vec<_>>();
+        let work = &work;
+        pool.scoped(|scope| {
+            for (share, result) in results.iter_mut().enumerate() {
+                scope.execute(move || *result = work(share, shares));
+            }
+        });
+        results
+    }
+
+    // Check every pair of particles; the reference for the other methods
+    pub fn check_collisions_brute_force(&self) -> usize {
+        let mut collision_count = 0;
+        for i in 0..self.particles.len() {
+            for j in (i + 1)..self.particles.len() {
+                if self.particles[i].collide(&self.particles[j], &self.config) {
+                    collision_count += 1;
+                }
+            }
+        }
+        collision_count
+    }
+
+    // Check only pairs in neighbouring cells of a spatial grid, falling back to
+    // brute force where a grid cannot be built
+    pub fn check_collisions_grid(&self) -> usize {
+        let grid = match build_grid(&self.particles, &self.config) {
+            Some(grid) => grid,
+            None => return self.check_collisions_brute_force(),
+        };
+        let mut collision_count = 0;
+        grid.for_each_candidate_pair(|i, j| {
+            if self.particles[i].collide(&self.particles[j], &self.config) {
+                collision_count += 1;
+            }
+        });
+        collision_count
+    }
+
+    // Get a handle to the shared collision counter so threads can update it
+    pub fn collision_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.collision_count)
+    }
+
+    // Get the total number of collisions
+    pub fn get_collision_count(&self) -> usize {
+        self.collision_count.load(Ordering::SeqCst)
+    }
+
+    // Get the number of particles of each species, step by step
+    pub fn population(&self) -> &Population {
+        &self.population
+    }
+
+    // Keep the population after every step from now on, rather than only the
+    // first and latest
+    pub fn keep_population(&mut self) {
+        self.population.keep_history();
+    }
+
+    // Get the collisions between each pair of species that have collided, by
+    // species name
+    pub fn get_species_collisions(&self) -> BTreeMap<(String, String), usize> {
+        self.species_collisions
+            .iter()
+            .map(|(&(a, b), &count)| {
+                let name = |species| self.config.species_name(species).to_string();
+                ((name(a), name(b)), count)
+            })
+            .collect()
+    }
+
+    // Get the number of pairs in contact summed over every step, i.e. the
+    // collision total as it was counted before collisions became events
+    pub fn get_overlap_count(&self) -> usize {
+        self.overlap_count.load(Ordering::SeqCst)
+    }
+}
+
+// Build the spatial grid for a collision check, or None to use brute force
+pub(crate) fn collision_grid(
+    particles: &[Particle],
+    config: &SimulationConfig,
+) -> Option<SpatialGrid> {
+    match config.collision_method {
+        CollisionMethod::Grid => build_grid(particles, config),
+        CollisionMethod::BruteForce => None,
+    }
+}
+
+// Build a grid for the particles, or None where a grid cannot help. A zero
+// reach never collides and would make zero-sized cells, and periodic walls
+// need at least three cells a side to wrap round.
+fn build_grid(particles: &[Particle], config: &SimulationConfig) -> Option<SpatialGrid> {
+    let reach = contact_reach(particles);
+    if reach <= 0.0 {
+        return None;
+    }
+    match config.boundary {
+        Boundary::Periodic => {
+            SpatialGrid::periodic(particles, reach, config.enclosure_size, config.dimensions)
+        }
+        _ => Some(SpatialGrid::new(particles, reach, config.dimensions)),
+    }
+}
+
+// Get the largest centre distance at which two of the particles can be in
+// contact: the sum of the two largest radii. Grid cells are made this wide so
+// pairs of any sizes are found, though a few very large particles make the
+// cells coarse for everyone else.
+fn contact_reach(particles: &[Particle]) -> f32 {
+    let (first, second) = particles
+        .iter()
+        .fold((0.0f32, 0.0f32), |(first, second), particle| {
+            let radius = particle.radius();
+            if radius > first {
+                (radius, first)
+            } else {
+                (first, second.max(radius))
+            }
+        });
+    first + second
+}
+
+// Count the collisions in share `share` of `shares` of the candidate pairs.
+// Summed over every share this gives the full count, whichever the broad phase.
+pub(crate) fn count_collision_share(
+    particles: &[Particle],
+    config: &SimulationConfig,
+    grid: Option<&SpatialGrid>,
+    share: usize,
+    shares: usize,
+) -> usize {
+    let mut collision_count = 0;
+    for_each_contact_in_share(particles, config, grid, share, shares, |_, _| {
+        collision_count += 1
+    });
+    collision_count
+}
+
+// Find the pairs in contact within one share of the candidate pairs
+pub(crate) fn contact_share(
+    particles: &[Particle],
+    config: &SimulationConfig,
+    grid: Option<&SpatialGrid>,
+    share: usize,
+    shares: usize,
+) -> Vec<Contact> {
+    let mut contacts = Vec::new();
+    for_each_contact_in_share(particles, config, grid, share, shares, |i, j| {
+        contacts.push(Contact::between(&particles[i], &particles[j], config))
+    });
+    contacts
+}
+
+// Call `f` with each colliding pair in share `share` of `shares` of the candidate pairs
+fn for_each_contact_in_share<F: FnMut(usize, usize)>(
+    particles: &[Particle],
+    config: &SimulationConfig,
+    grid: Option<&SpatialGrid>,
+    share: usize,
+    shares: usize,
+    mut f: F,
+) {
+    match grid {
+        Some(grid) => {
+            let cells = grid.cells();
+            let chunk_size = cells.len().div_ceil(shares).max(1);
+            if let Some(cells) = cells.chunks(chunk_size).nth(share) {
+                for &cell in cells {
+                    grid.for_each_pair_from(cell, |i, j| {
+                        if particles[i].collide(&particles[j], config) {
+                            f(i, j);
+                        }
+                    });
+                }
+            }
+        }
+        None => {
+            // Rows get shorter as i grows, so each share takes every
+            // `shares`-th row rather than a contiguous block
+            for i in (share..particles.len()).step_by(shares) {
+                for j in (i + 1)..particles.len() {
+                    if particles[i].collide(&particles[j], config) {
+                        f(i, j);
+                    }
+                }
+            }
+        }
+    }
+}
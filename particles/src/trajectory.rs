@@ -0,0 +1,663 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::{Boundary, Particle, SimulationConfig};
+
+// Marks the start of a binary trajectory, followed by the format version
+pub const TRAJECTORY_MAGIC: &[u8; 4] = b"PTRJ";
+pub const TRAJECTORY_VERSION: u32 = 1;
+
+// The columns of an extended XYZ frame
+const XYZ_PROPERTIES: &str = "species:S:1:pos:R:3:velo:R:3:id:I:1:mass:R:1:radius:R:1";
+
+// The most particles room is made for before a frame is read. Counts come
+// from the file, so a damaged one must not reserve more than this up front.
+const PREALLOCATED_PARTICLES: usize = 1 << 16;
+
+// Every particle in the system after a step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub step: u64,
+    pub particles: Vec<Particle>,
+}
+
+impl Frame {
+    // Describe the first way this frame differs from `other`, or None if they
+    // match. Species are compared by name, as each file numbers its own.
+    // Positions, velocities, masses and radii may differ by up to `tolerance`.
+    pub fn difference(
+        &self,
+        species: &[String],
+        other: &Frame,
+        other_species: &[String],
+        tolerance: f32,
+    ) -> Option<String> {
+        if self.step != other.step {
+            return Some(format!("step {} against step {}", self.step, other.step));
+        }
+        if self.particles.len() != other.particles.len() {
+            return Some(format!(
+                "{} particles against {}",
+                self.particles.len(),
+                other.particles.len()
+            ));
+        }
+        let name = |names: &[String], index: usize| names.get(index).cloned().unwrap_or_default();
+        let differs = |a: &[f32], b: &[f32]| {
+            a.iter().zip(b).any(|(a, b)| {
+                // A NaN on either side only matches the same NaN
+                let order = (a - b).abs().partial_cmp(&tolerance);
+                a.to_bits() != b.to_bits() && order.is_none_or(|order| order.is_gt())
+            })
+        };
+        for (a, b) in self.particles.iter().zip(&other.particles) {
+            if a.id() != b.id() {
+                return Some(format!("particle {} against particle {}", a.id(), b.id()));
+            }
+            let (first, second) = (name(species, a.species()), name(other_species, b.species()));
+            if first != second {
+                return Some(format!(
+                    "particle {} is {} against {}",
+                    a.id(),
+                    first,
+                    second
+                ));
+            }
+            for (what, x, y) in [
+                ("position", a.position().to_vec(), b.position().to_vec()),
+                ("velocity", a.velocity().to_vec(), b.velocity().to_vec()),
+                ("mass", vec![a.mass()], vec![b.mass()]),
+                ("radius", vec![a.radius()], vec![b.radius()]),
+            ] {
+                if differs(&x, &y) {
+                    let show = |values: &[f32]| match values {
+                        [value] => value.to_string(),
+                        _ => format!("{:?}", values),
+                    };
+                    return Some(format!(
+                        "particle {} {} {} against {}",
+                        a.id(),
+                        what,
+                        show(&x),
+                        show(&y)
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+// File formats for a trajectory
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrajectoryFormat {
+    Csv,    // One row per particle per frame, or a row with only the step for an empty frame
+    Xyz,    // Extended XYZ, as read by visualisation tools such as OVITO and ASE
+    Binary, // Little-endian records; see TrajectoryWriter::write
+}
+
+impl TrajectoryFormat {
+    // Pick the format from a file extension: .csv, .xyz / .extxyz or .bin,
+    // optionally followed by .gz for a compressed file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let path = match is_compressed(path) {
+            true => Path::new(path.file_stem()?),
+            false => path,
+        };
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Some(TrajectoryFormat::Csv),
+            Some("xyz") | Some("extxyz") => Some(TrajectoryFormat::Xyz),
+            Some("bin") => Some(TrajectoryFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+// Check whether a trajectory file is gzip-compressed, going by its name
+pub fn is_compressed<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().and_then(|ext| ext.to_str()) == Some("gz")
+}
+
+// A trajectory file being written, gzip-compressed if its name ends in .gz
+pub enum TrajectoryFile {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl TrajectoryFile {
+    // Create the file, compressing it if asked
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(&path)?);
+        match is_compressed(&path) {
+            true => Ok(TrajectoryFile::Gzip(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))),
+            false => Ok(TrajectoryFile::Plain(file)),
+        }
+    }
+
+    // Flush everything to disk, ending the gzip stream if there is one
+    pub fn close(self) -> io::Result<()> {
+        match self {
+            TrajectoryFile::Plain(mut out) => out.flush(),
+            TrajectoryFile::Gzip(gzip) => gzip.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for TrajectoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TrajectoryFile::Plain(out) => out.write(buf),
+            TrajectoryFile::Gzip(gzip) => gzip.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TrajectoryFile::Plain(out) => out.flush(),
+            TrajectoryFile::Gzip(gzip) => gzip.flush(),
+        }
+    }
+}
+
+// Writes frames of a trajectory
+pub struct TrajectoryWriter<W: Write> {
+    out: W,
+    format: TrajectoryFormat,
+    species: Vec<String>, // Species names, by index
+    dimensions: usize,
+    enclosure_size: f32,
+    periodic: bool,
+}
+
+impl TrajectoryWriter<TrajectoryFile> {
+    // Create a trajectory file, picking the format from its name
+    pub fn create<P: AsRef<Path>>(path: P, config: &SimulationConfig) -> io::Result<Self> {
+        let format = TrajectoryFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "trajectory must end in .csv, .xyz or .bin, optionally with .gz",
+            )
+        })?;
+        TrajectoryWriter::new(TrajectoryFile::create(path)?, format, config)
+    }
+}
+
+impl<W: Write> TrajectoryWriter<W> {
+    // Start a trajectory of a system with the given config. CSV files begin
+    // with a header row, binary files with a header describing the system.
+    pub fn new(
+        mut out: W,
+        format: TrajectoryFormat,
+        config: &SimulationConfig,
+    ) -> io::Result<Self> {
+        let species = config
+            .species_list()
+            .into_iter()
+            .map(|species| species.name)
+            .collect::<Vec<_>>();
+        match format {
+            TrajectoryFormat::Csv => {
+                writeln!(out, "step,id,species,mass,radius,x,y,z,vx,vy,vz")?;
+            }
+            TrajectoryFormat::Xyz => {}
+            TrajectoryFormat::Binary => {
+                // magic, version, dimensions, enclosure size, then each species
+                // name as a length and UTF-8 bytes
+                out.write_all(TRAJECTORY_MAGIC)?;
+                out.write_all(&TRAJECTORY_VERSION.to_le_bytes())?;
+                out.write_all(&(config.dimensions as u32).to_le_bytes())?;
+                out.write_all(&config.enclosure_size.to_le_bytes())?;
+                out.write_all(&(species.len() as u32).to_le_bytes())?;
+                for name in &species {
+                    out.write_all(&(name.len() as u32).to_le_bytes())?;
+                    out.write_all(name.as_bytes())?;
+                }
+            }
+        }
+        Ok(TrajectoryWriter {
+            out,
+            format,
+            species,
+            dimensions: config.dimensions,
+            enclosure_size: config.enclosure_size,
+            periodic: config.boundary == Boundary::Periodic,
+        })
+    }
+
+    // Append one frame
+    pub fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        match self.format {
+            TrajectoryFormat::Csv => {
+                // An empty frame still gets a row, with only its step, so
+                // that reading it back keeps the step
+                if frame.particles.is_empty() {
+                    writeln!(self.out, "{},,,,,,,,,,", frame.step)?;
+                }
+                for particle in &frame.particles {
+                    let ([x, y, z], [vx, vy, vz]) = (particle.position(), particle.velocity());
+                    writeln!(
+                        self.out,
+                        "{},{},{},{},{},{},{},{},{},{},{}",
+                        frame.step,
+                        particle.id(),
+                        self.species[particle.species()],
+                        particle.mass(),
+                        particle.radius(),
+                        x,
+                        y,
+                        z,
+                        vx,
+                        vy,
+                        vz
+                    )?;
+                }
+                Ok(())
+            }
+            TrajectoryFormat::Xyz => self.write_xyz(frame),
+            // step: u64, count: u64, then per particle id: u64, species: u32,
+            // mass: f32, radius: f32, position: 3 x f32, velocity: 3 x f32
+            TrajectoryFormat::Binary => {
+                self.out.write_all(&frame.step.to_le_bytes())?;
+                self.out
+                    .write_all(&(frame.particles.len() as u64).to_le_bytes())?;
+                for particle in &frame.particles {
+                    self.out.write_all(&particle.id().to_le_bytes())?;
+                    self.out
+                        .write_all(&(particle.species() as u32).to_le_bytes())?;
+                    self.out.write_all(&particle.mass().to_le_bytes())?;
+                    self.out.write_all(&particle.radius().to_le_bytes())?;
+                    for value in particle.position().iter().chain(&particle.velocity()) {
+                        self.out.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Write a frame in extended XYZ: the particle count, a comment line of
+    // key=value pairs describing the columns and the enclosure, then one line
+    // per particle
+    fn write_xyz(&mut self, frame: &Frame) -> io::Result<()> {
+        let size = self.enclosure_size;
+        // A 2D enclosure has no depth
+        let depth = if self.dimensions == 2 { 0.0 } else { size };
+        let wraps = if self.periodic { "T" } else { "F" };
+        let pbc = match self.dimensions {
+            2 => format!("{} {} F", wraps, wraps),
+            _ => format!("{} {} {}", wraps, wraps, wraps),
+        };
+        writeln!(self.out, "{}", frame.particles.len())?;
+        writeln!(
+            self.out,
+            "Lattice=\"{} 0 0 0 {} 0 0 0 {}\" Properties={} step={} pbc=\"{}\"",
+            size, size, depth, XYZ_PROPERTIES, frame.step, pbc
+        )?;
+        for particle in &frame.particles {
+            let ([x, y, z], [vx, vy, vz]) = (particle.position(), particle.velocity());
+            writeln!(
+                self.out,
+                "{} {} {} {} {} {} {} {} {} {}",
+                self.species[particle.species()],
+                x,
+                y,
+                z,
+                vx,
+                vy,
+                vz,
+                particle.id(),
+                particle.mass(),
+                particle.radius()
+            )?;
+        }
+        Ok(())
+    }
+
+    // Flush anything buffered and hand back the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// A trajectory file being read, decompressed if its name ends in .gz
+pub enum TrajectoryInput {
+    Plain(BufReader<File>),
+    Gzip(BufReader<MultiGzDecoder<BufReader<File>>>),
+}
+
+impl TrajectoryInput {
+    // Open the file, decompressing it if needed
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = BufReader::new(File::open(&path)?);
+        match is_compressed(&path) {
+            true => Ok(TrajectoryInput::Gzip(BufReader::new(MultiGzDecoder::new(
+                file,
+            )))),
+            false => Ok(TrajectoryInput::Plain(file)),
+        }
+    }
+}
+
+impl Read for TrajectoryInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            TrajectoryInput::Plain(input) => input.read(buf),
+            TrajectoryInput::Gzip(output) => output.read(buf),
+        }
+    }
+}
+
+impl BufRead for TrajectoryInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            TrajectoryInput::Plain(input) => input.fill_buf(),
+            TrajectoryInput::Gzip(output) => output.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            TrajectoryInput::Plain(input) => input.consume(amount),
+            TrajectoryInput::Gzip(output) => output.consume(amount),
+        }
+    }
+}
+
+// Reads back the frames of a trajectory written by TrajectoryWriter.
+// Particles are given species by index into `species()`, in the order the
+// names first appear in the file.
+pub struct TrajectoryReader<R: BufRead> {
+    input: R,
+    format: TrajectoryFormat,
+    species: Vec<String>,
+    dimensions: Option<usize>, // As recorded, if the format records it
+    enclosure_size: Option<f32>,
+    pending: Option<(u64, Option<Particle>)>, // A CSV row read past the end of the last frame
+}
+
+impl TrajectoryReader<TrajectoryInput> {
+    // Open a trajectory file, picking the format from its name
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let format = TrajectoryFormat::from_path(&path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "trajectory must end in .csv, .xyz or .bin, optionally with .gz",
+            )
+        })?;
+        TrajectoryReader::new(TrajectoryInput::open(path)?, format)
+    }
+}
+
+impl<R: BufRead> TrajectoryReader<R> {
+    // Start reading a trajectory in the given format, checking its header
+    pub fn new(mut input: R, format: TrajectoryFormat) -> io::Result<Self> {
+        let mut species = Vec::new();
+        let (mut dimensions, mut enclosure_size) = (None, None);
+        match format {
+            TrajectoryFormat::Csv => {
+                let mut header = String::new();
+                input.read_line(&mut header)?;
+                if header.trim_end() != "step,id,species,mass,radius,x,y,z,vx,vy,vz" {
+                    return Err(invalid("not a trajectory CSV file"));
+                }
+            }
+            TrajectoryFormat::Xyz => {}
+            TrajectoryFormat::Binary => {
+                let mut magic = [0; 4];
+                input.read_exact(&mut magic)?;
+                if &magic != TRAJECTORY_MAGIC {
+                    return Err(invalid("not a binary trajectory"));
+                }
+                let version = read_u32(&mut input)?;
+                if version != TRAJECTORY_VERSION {
+                    return Err(invalid(&format!(
+                        "binary trajectory version {} is not supported",
+                        version
+                    )));
+                }
+                dimensions = Some(read_u32(&mut input)? as usize);
+                enclosure_size = Some(read_f32(&mut input)?);
+                for _ in 0..read_u32(&mut input)? {
+                    let length = read_u32(&mut input)? as u64;
+                    let mut name = Vec::new();
+                    if input.by_ref().take(length).read_to_end(&mut name)? as u64 != length {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    species.push(String::from_utf8(name).map_err(|_| invalid("bad species name"))?);
+                }
+            }
+        }
+        Ok(TrajectoryReader {
+            input,
+            format,
+            species,
+            dimensions,
+            enclosure_size,
+            pending: None,
+        })
+    }
+
+    // Get the names of the species seen so far
+    pub fn species(&self) -> &[String] {
+        &self.species
+    }
+
+    // Get the number of dimensions the trajectory was recorded in. Binary
+    // files give it in their header and extended XYZ files in each frame, so
+    // it is known once the first frame has been read; CSV files never give it.
+    pub fn dimensions(&self) -> Option<usize> {
+        self.dimensions
+    }
+
+    // Get the side length of the enclosure the trajectory was recorded in,
+    // known as for dimensions()
+    pub fn enclosure_size(&self) -> Option<f32> {
+        self.enclosure_size
+    }
+
+    // Read the next frame, or None at the end of the file
+    pub fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        match self.format {
+            TrajectoryFormat::Csv => self.next_csv_frame(),
+            TrajectoryFormat::Xyz => self.next_xyz_frame(),
+            TrajectoryFormat::Binary => self.next_binary_frame(),
+        }
+    }
+
+    // Rows belong to the same frame while their step stays the same. An
+    // empty frame is a single row with nothing but the step.
+    fn next_csv_frame(&mut self) -> io::Result<Option<Frame>> {
+        let (step, first) = match self.pending.take() {
+            Some(row) => row,
+            None => match self.csv_row()? {
+                Some(row) => row,
+                None => return Ok(None),
+            },
+        };
+        let mut particles = first.into_iter().collect::<Vec<_>>();
+        while let Some((next, particle)) = self.csv_row()? {
+            if next != step {
+                self.pending = Some((next, particle));
+                break;
+            }
+            particles.extend(particle);
+        }
+        Ok(Some(Frame { step, particles }))
+    }
+
+    fn csv_row(&mut self) -> io::Result<Option<(u64, Option<Particle>)>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(None);
+        }
+        let fields = line.trim_end().split(',').collect::<Vec<_>>();
+        if fields.len() != 11 {
+            return Err(invalid(&format!(
+                "bad trajectory row '{}'",
+                line.trim_end()
+            )));
+        }
+        let step = parse(fields[0])?;
+        if fields[1..].iter().all(|field| field.is_empty()) {
+            return Ok(Some((step, None)));
+        }
+        let numbers = parse_floats(&[&fields[3..5], &fields[5..]].concat())?;
+        let species = self.species_index(fields[2]);
+        let particle = Particle::at(parse(fields[1])?, [numbers[2], numbers[3], numbers[4]])
+            .with_velocity([numbers[5], numbers[6], numbers[7]])
+            .with_mass(numbers[0])
+            .with_radius(numbers[1])
+            .with_species(species);
+        Ok(Some((step, Some(particle))))
+    }
+
+    fn next_xyz_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(None);
+        }
+        let count: usize = parse(line.trim())?;
+        line.clear();
+        self.input.read_line(&mut line)?;
+        if !line.contains(&format!("Properties={}", XYZ_PROPERTIES)) {
+            return Err(invalid("unexpected extended XYZ columns"));
+        }
+        let step = line
+            .split_whitespace()
+            .find_map(|pair| pair.strip_prefix("step="))
+            .ok_or_else(|| invalid("extended XYZ frame has no step"))?;
+        let step = parse(step)?;
+        self.read_lattice(&line)?;
+
+        let mut particles = Vec::with_capacity(count.min(PREALLOCATED_PARTICLES));
+        for _ in 0..count {
+            line.clear();
+            self.input.read_line(&mut line)?;
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != 10 {
+                return Err(invalid(&format!(
+                    "bad trajectory row '{}'",
+                    line.trim_end()
+                )));
+            }
+            let numbers = parse_floats(&[&fields[1..7], &fields[8..]].concat())?;
+            let species = self.species_index(fields[0]);
+            particles.push(
+                Particle::at(parse(fields[7])?, [numbers[0], numbers[1], numbers[2]])
+                    .with_velocity([numbers[3], numbers[4], numbers[5]])
+                    .with_mass(numbers[6])
+                    .with_radius(numbers[7])
+                    .with_species(species),
+            );
+        }
+        Ok(Some(Frame { step, particles }))
+    }
+
+    fn next_binary_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut step = [0; 8];
+        match self.input.read_exact(&mut step) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let step = u64::from_le_bytes(step);
+        let count = read_u64(&mut self.input)?;
+        let mut particles = Vec::with_capacity(count.min(PREALLOCATED_PARTICLES as u64) as usize);
+        for _ in 0..count {
+            let id = read_u64(&mut self.input)?;
+            let species = read_u32(&mut self.input)? as usize;
+            if species >= self.species.len() {
+                return Err(invalid("particle of an unknown species"));
+            }
+            let mut values = [0.0; 8];
+            for value in values.iter_mut() {
+                *value = read_f32(&mut self.input)?;
+            }
+            particles.push(
+                Particle::at(id, [values[2], values[3], values[4]])
+                    .with_velocity([values[5], values[6], values[7]])
+                    .with_mass(values[0])
+                    .with_radius(values[1])
+                    .with_species(species),
+            );
+        }
+        Ok(Some(Frame { step, particles }))
+    }
+
+    // Take the enclosure from the Lattice of an extended XYZ comment line:
+    // a diagonal cell, with no depth for a 2D enclosure
+    fn read_lattice(&mut self, line: &str) -> io::Result<()> {
+        let lattice = line
+            .split_once("Lattice=\"")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(lattice, _)| lattice)
+            .ok_or_else(|| invalid("extended XYZ frame has no lattice"))?;
+        let cell = parse_floats(&lattice.split_whitespace().collect::<Vec<_>>())?;
+        if cell.len() != 9 {
+            return Err(invalid(&format!("bad extended XYZ lattice '{}'", lattice)));
+        }
+        self.enclosure_size = Some(cell[0]);
+        self.dimensions = Some(if cell[8] == 0.0 { 2 } else { 3 });
+        Ok(())
+    }
+
+    // Get the index of a species by name, adding it if it is new
+    fn species_index(&mut self, name: &str) -> usize {
+        match self.species.iter().position(|species| species == name) {
+            Some(index) => index,
+            None => {
+                self.species.push(name.to_string());
+                self.species.len() - 1
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TrajectoryReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn parse<T: std::str::FromStr>(field: &str) -> io::Result<T> {
+    field
+        .parse()
+        .map_err(|_| invalid(&format!("bad number '{}' in trajectory", field)))
+}
+
+fn parse_floats(fields: &[&str]) -> io::Result<Vec<f32>> {
+    fields.iter().map(|field| parse(field)).collect()
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(input: &mut R) -> io::Result<f32> {
+    Ok(f32::from_bits(read_u32(input)?))
+}
@@ -0,0 +1,124 @@
+// Which anomalies can and cannot occur when AtomicParticles is shared
+// between threads without a lock
+
+use particles::{AtomicParticles, Particle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const WRITES: u32 = 200_000;
+
+fn particle(value: u32) -> Particle {
+    Particle::from_position(0, value as f32, value as f32)
+}
+
+// Cannot occur: x and y are stored together, so a reader never sees the x of
+// one store with the y of another
+#[test]
+fn packed_positions_are_never_torn() {
+    let store = AtomicParticles::from_particles(&[particle(0)]);
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let (x, y) = store.load(0).get_position();
+                    assert_eq!(x, y, "torn read");
+                }
+            });
+        }
+        for value in 1..WRITES {
+            store.store(0, &particle(value));
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+// Cannot occur in 3D either: z lives outside the packed word, but the
+// sequence number makes a reader retry rather than mix two stores
+#[test]
+fn positions_with_depth_are_never_torn() {
+    let at = |value: u32| Particle::at(0, [value as f32; 3]);
+    let store = AtomicParticles::new(&[at(0)], 3);
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let [x, y, z] = store.load(0).position();
+                    assert!(x == y && y == z, "torn read ({}, {}, {})", x, y, z);
+                }
+            });
+        }
+        for value in 1..WRITES {
+            store.store(0, &at(value));
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+// Cannot occur: once a reader has seen a position it never sees an older one
+#[test]
+fn loads_never_go_backwards() {
+    let store = AtomicParticles::from_particles(&[particle(0)]);
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut last = 0.0;
+            while !done.load(Ordering::Relaxed) {
+                let (x, _) = store.load(0).get_position();
+                assert!(x >= last, "saw {} after {}", x, last);
+                last = x;
+            }
+        });
+        for value in 1..WRITES {
+            store.store(0, &particle(value));
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+// Cannot occur: joining the writer makes every store visible to the joiner
+#[test]
+fn join_publishes_every_store() {
+    let store = AtomicParticles::from_particles(&vec![particle(0); 64]);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..store.len() {
+                store.store(i, &particle(i as u32 + 1));
+            }
+        });
+    });
+    for (i, particle) in store.snapshot().iter().enumerate() {
+        assert_eq!(particle.get_position(), ((i + 1) as f32, (i + 1) as f32));
+    }
+}
+
+// Can occur: a pass over the store while a mover runs sees some particles
+// before a move and others after it. The mover writes every particle at each
+// step in turn until a snapshot catches it part way through a step.
+#[test]
+fn snapshot_can_mix_steps() {
+    let store = AtomicParticles::from_particles(&vec![particle(0); 4096]);
+    let mixed = AtomicBool::new(false);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut step = 0;
+            while !mixed.load(Ordering::Relaxed) && Instant::now() < deadline {
+                step += 1;
+                for i in 0..store.len() {
+                    store.store(i, &particle(step));
+                }
+            }
+        });
+        while !mixed.load(Ordering::Relaxed) && Instant::now() < deadline {
+            let snapshot = store.snapshot();
+            let first = snapshot[0].get_position();
+            if snapshot.iter().any(|p| p.get_position() != first) {
+                mixed.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+    assert!(mixed.load(Ordering::Relaxed), "no snapshot mixed two steps");
+}
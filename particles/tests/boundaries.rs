@@ -0,0 +1,84 @@
+// Behaviour of each Boundary at the walls of the enclosure
+
+mod common;
+
+use common::seeded;
+use particles::{
+    stream_rng, Boundary, CollisionMethod, Contact, MovementModel, Particle, ParticleSystem,
+    RadiusDistribution, SimulationConfig,
+};
+
+fn config(boundary: Boundary) -> SimulationConfig {
+    SimulationConfig {
+        movement: MovementModel::Ballistic,
+        boundary,
+        ..seeded(3)
+    }
+}
+
+// Periodic walls: particles either side of a wall are neighbours, and the
+// contact is described the short way round
+#[test]
+fn periodic_contacts_cross_the_walls() {
+    let config = config(Boundary::Periodic);
+    let left = Particle::from_position(0, 0.05, 5.0).with_radius(0.1);
+    let right = Particle::from_position(1, 9.95, 5.0).with_radius(0.1);
+    assert!(left.collide(&right, &config));
+
+    let contact = Contact::between(&left, &right, &config);
+    assert!((contact.distance - 0.1).abs() < 1e-5);
+    assert!(contact.midpoint[0] < 0.01 || contact.midpoint[0] > 9.99);
+
+    let clamped = SimulationConfig {
+        boundary: Boundary::Clamp,
+        ..config
+    };
+    assert!(!left.collide(&right, &clamped));
+}
+
+// The grid wraps round periodic walls, so it finds the same contacts as brute force
+#[test]
+fn periodic_grid_matches_brute_force() {
+    for radius in [0.1, 0.5, 1.5, 2.0] {
+        let grid = SimulationConfig {
+            radius: RadiusDistribution::Fixed(radius),
+            ..config(Boundary::Periodic)
+        };
+        let brute_force = SimulationConfig {
+            collision_method: CollisionMethod::BruteForce,
+            ..grid.clone()
+        };
+        let mut grid = ParticleSystem::new(grid);
+        let mut brute_force = ParticleSystem::new(brute_force);
+        assert_eq!(grid.step(100), brute_force.step(100), "radius {}", radius);
+    }
+}
+
+#[test]
+fn reflecting_walls_keep_particles_inside() {
+    let config = config(Boundary::Reflect);
+    let mut system = ParticleSystem::new(config.clone());
+    system.step(500);
+    assert_eq!(system.get_particle_count(), config.num_of_particles);
+    assert!(system
+        .particles()
+        .iter()
+        .all(|particle| particle.is_inside(&config)));
+}
+
+#[test]
+fn absorbing_walls_remove_particles() {
+    let mut system = ParticleSystem::new(config(Boundary::Absorbing));
+    system.step(500);
+    assert!(system.get_particle_count() < 100);
+}
+
+// A clamped particle stops moving into the wall but keeps sliding along it
+#[test]
+fn clamping_drops_velocity_into_the_wall() {
+    let config = config(Boundary::Clamp);
+    let mut particle = Particle::from_position(0, 9.5, 5.0).with_velocity([1.0, 0.5, 0.0]);
+    particle.move_particle(&config, &mut stream_rng(0, 0));
+    assert_eq!(particle.position(), [10.0, 5.5, 0.0]);
+    assert_eq!(particle.velocity(), [0.0, 0.5, 0.0]);
+}
@@ -0,0 +1,102 @@
+// Parsing the simulator command line, and the exit codes of a simulator run
+
+use std::fs;
+use std::process::ExitCode;
+
+use particles::{run_simulator, Cli, ConfigError, Strategy};
+
+fn parse(list: &[&str]) -> Result<Cli, ConfigError> {
+    Cli::parse(list.iter().map(|arg| arg.to_string()), Strategy::SingleLock)
+}
+
+#[test]
+fn values_follow_an_equals_sign_or_a_space() {
+    let joined = parse(&["--particles=7", "--steps=9"]).unwrap();
+    let spaced = parse(&["--particles", "7", "--steps", "9"]).unwrap();
+    assert_eq!(joined, spaced);
+    assert_eq!(joined.config.num_of_particles, 7);
+    assert_eq!(joined.config.steps, 9);
+}
+
+#[test]
+fn bad_arguments_are_refused() {
+    assert!(matches!(
+        parse(&["--steps"]),
+        Err(ConfigError::MissingValue(key)) if key == "--steps"
+    ));
+    assert!(matches!(
+        parse(&["--colour", "red"]),
+        Err(ConfigError::UnknownKey(_))
+    ));
+    assert!(matches!(
+        parse(&["run"]),
+        Err(ConfigError::UnknownKey(key)) if key == "run"
+    ));
+    assert!(matches!(
+        parse(&["--strategy", "fastest"]),
+        Err(ConfigError::InvalidValue { key, .. }) if key == "--strategy"
+    ));
+}
+
+#[test]
+fn options_set_the_run() {
+    let cli = parse(&["--strategy", "split", "--threads", "3"]).unwrap();
+    assert_eq!(cli.run.strategy, Strategy::Split);
+    assert_eq!((cli.run.move_threads, cli.run.collision_threads), (3, 3));
+    assert_eq!(parse(&[]).unwrap().run.strategy, Strategy::SingleLock);
+
+    assert_eq!(parse(&[]).unwrap().verbosity, 1);
+    assert_eq!(parse(&["-q"]).unwrap().verbosity, 0);
+    assert_eq!(parse(&["-v"]).unwrap().verbosity, 2);
+    assert_eq!(parse(&["--verbose", "-v"]).unwrap().verbosity, 3);
+    assert_eq!(parse(&["-q", "-v"]).unwrap().verbosity, 2);
+
+    // Double-buffer and rwlock have one mover, which --threads leaves alone
+    for strategy in ["double-buffer", "rwlock"] {
+        let cli = parse(&["--threads", "4", "--strategy", strategy]).unwrap();
+        assert_eq!((cli.run.move_threads, cli.run.collision_threads), (1, 4));
+        assert!(parse(&["--strategy", strategy, "--move-threads", "2"]).is_err());
+    }
+
+    assert!(parse(&["--help"]).unwrap().help);
+    assert!(parse(&["-h"]).unwrap().help);
+    assert!(!parse(&[]).unwrap().help);
+}
+
+// Settings given on the command line beat those of --config, wherever they are
+#[test]
+fn options_override_the_config_file_in_any_order() {
+    let path = std::env::temp_dir().join(format!("cli-{}.toml", std::process::id()));
+    fs::write(&path, "num_of_particles = 50\nsteps = 20\n").unwrap();
+    let file = path.to_str().unwrap();
+    let before = parse(&["--particles", "7", "--config", file]).unwrap();
+    let after = parse(&["--config", file, "--particles", "7"]).unwrap();
+    let alone = parse(&["--config", file]).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(before, after);
+    assert_eq!(
+        (before.config.num_of_particles, before.config.steps),
+        (7, 20)
+    );
+    assert_eq!(alone.config.num_of_particles, 50);
+}
+
+#[test]
+fn runs_give_an_exit_code() {
+    let run = |list: &[&str]| {
+        let args = list.iter().map(|arg| arg.to_string());
+        run_simulator("particles", args, Strategy::SingleLock)
+    };
+    assert_eq!(run(&["--help"]), ExitCode::SUCCESS);
+    assert_eq!(
+        run(&["-q", "--particles", "10", "--steps", "5"]),
+        ExitCode::SUCCESS
+    );
+    assert_eq!(run(&["--strategy", "fastest"]), ExitCode::from(2));
+    assert_eq!(run(&["--enclosure-size", "0"]), ExitCode::from(2));
+    assert_eq!(
+        run(&["-q", "--steps", "1", "--events", "/nonexistent/log.csv"]),
+        ExitCode::from(1)
+    );
+}
@@ -0,0 +1,112 @@
+// Every way of finding colliding pairs agrees with checking every pair
+
+mod common;
+
+use common::{seeded, system_of};
+use particles::{CollisionMethod, Particle, ParticleSystem, RadiusDistribution, SimulationConfig};
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        num_of_particles: 500,
+        radius: RadiusDistribution::Fixed(0.3),
+        ..seeded(4)
+    }
+}
+
+// The grid only looks at neighbouring cells, but finds every pair in contact
+#[test]
+fn grid_matches_brute_force() {
+    for dimensions in [2, 3] {
+        let mut system = ParticleSystem::new(SimulationConfig {
+            dimensions,
+            ..config()
+        });
+        for _ in 0..20 {
+            let expected = system.check_collisions_brute_force();
+            assert!(expected > 0);
+            assert_eq!(system.check_collisions_grid(), expected, "{}D", dimensions);
+            system.tick();
+        }
+    }
+
+    let brute_force = SimulationConfig {
+        collision_method: CollisionMethod::BruteForce,
+        ..config()
+    };
+    assert_eq!(
+        ParticleSystem::new(config()).step(100),
+        ParticleSystem::new(brute_force).step(100)
+    );
+}
+
+// A 2D grid ignores z, so particles off the plane, as in a 3D trajectory
+// replayed in 2D, are still found as brute force finds them
+#[test]
+fn flat_grid_matches_brute_force_off_the_plane() {
+    let deep = ParticleSystem::new(SimulationConfig {
+        dimensions: 3,
+        ..config()
+    });
+    let flat = ParticleSystem::from_frame(config(), deep.frame());
+    assert!(flat.check_collisions_brute_force() > 0);
+    assert_eq!(
+        flat.check_collisions_grid(),
+        flat.check_collisions_brute_force()
+    );
+}
+
+// Far out the cell index saturates rather than overflowing, and the pairs
+// crowded into the last cell are still all tested
+#[test]
+fn grid_copes_with_huge_enclosures() {
+    let at = |id, x: f32, y: f32| Particle::from_position(id, x, y).with_radius(0.5);
+    let particles = vec![
+        at(0, 1e30, 1e30),
+        at(1, 1e30, 1e30),
+        at(2, -1e30, 5.0),
+        at(3, -1e30, 5.2),
+        at(4, 5.0, 5.0),
+    ];
+    let system = system_of(
+        SimulationConfig {
+            enclosure_size: 1e30,
+            ..config()
+        },
+        particles,
+    );
+    assert_eq!(system.check_collisions_brute_force(), 2);
+    assert_eq!(system.check_collisions_grid(), 2);
+
+    let mut system = ParticleSystem::new(SimulationConfig {
+        enclosure_size: 1e30,
+        ..config()
+    });
+    system.step(5);
+}
+
+// Collision workers split the pairs between them and sum their tallies, so
+// they count exactly what a single thread counts
+#[test]
+fn collision_workers_match_serial() {
+    for method in [CollisionMethod::Grid, CollisionMethod::BruteForce] {
+        let serial = SimulationConfig {
+            collision_method: method,
+            ..config()
+        };
+        let pooled = SimulationConfig {
+            collision_workers: 4,
+            ..serial.clone()
+        };
+        let (mut serial, mut pooled) = (ParticleSystem::new(serial), ParticleSystem::new(pooled));
+        for _ in 0..20 {
+            assert_eq!(
+                pooled.check_collisions(),
+                serial.check_collisions(),
+                "{}",
+                method
+            );
+            assert_eq!(pooled.tick(), serial.tick(), "{}", method);
+        }
+        assert_eq!(pooled.get_collision_count(), serial.get_collision_count());
+    }
+}
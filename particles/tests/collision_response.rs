@@ -0,0 +1,186 @@
+// Conservation laws for Particle::resolve_collision and the collision response
+// phase of ParticleSystem
+
+mod common;
+
+use common::seeded;
+use particles::{
+    stream_rng, Boundary, MovementModel, Particle, ParticleSystem, RadiusDistribution,
+    SimulationConfig,
+};
+use rand::RngExt;
+
+const TOLERANCE: f32 = 1e-4;
+
+fn momentum(particles: &[Particle]) -> [f32; 3] {
+    particles.iter().fold([0.0; 3], |total, particle| {
+        let momentum = particle.momentum();
+        [
+            total[0] + momentum[0],
+            total[1] + momentum[1],
+            total[2] + momentum[2],
+        ]
+    })
+}
+
+fn kinetic_energy(particles: &[Particle]) -> f32 {
+    particles
+        .iter()
+        .map(|particle| particle.kinetic_energy())
+        .sum()
+}
+
+// A random pair of touching particles in 3D, approaching or not
+fn random_pair(seed: u64) -> (Particle, Particle) {
+    let mut rng = stream_rng(seed, 0);
+    let mut vector = |scale: f32| {
+        [
+            (rng.random::<f32>() * 2.0 - 1.0) * scale,
+            (rng.random::<f32>() * 2.0 - 1.0) * scale,
+            (rng.random::<f32>() * 2.0 - 1.0) * scale,
+        ]
+    };
+    let (v1, v2) = (vector(2.0), vector(2.0));
+    let direction = vector(1.0);
+    let length = direction.iter().map(|axis| axis * axis).sum::<f32>().sqrt();
+    let second_at = direction.map(|axis| 5.0 + 0.9 * axis / length);
+
+    let mut rng = stream_rng(seed, 1);
+    let (m1, m2) = (
+        0.1 + rng.random::<f32>() * 5.0,
+        0.1 + rng.random::<f32>() * 5.0,
+    );
+    let first = Particle::at(0, [5.0; 3])
+        .with_velocity(v1)
+        .with_mass(m1)
+        .with_radius(0.5);
+    let second = Particle::at(1, second_at)
+        .with_velocity(v2)
+        .with_mass(m2)
+        .with_radius(0.5);
+    (first, second)
+}
+
+fn assert_momentum(actual: [f32; 3], expected: [f32; 3]) {
+    for axis in 0..3 {
+        assert_close(actual[axis], expected[axis], "momentum");
+    }
+}
+
+fn assert_close(actual: f32, expected: f32, what: &str) {
+    assert!(
+        (actual - expected).abs() <= TOLERANCE * expected.abs().max(1.0),
+        "{}: {} != {}",
+        what,
+        actual,
+        expected
+    );
+}
+
+// Equal masses meeting head on swap velocities
+#[test]
+fn equal_masses_swap_velocities() {
+    let mut first = Particle::from_position(0, 4.95, 5.0)
+        .with_velocity([1.0, 0.0, 0.0])
+        .with_radius(0.1);
+    let mut second = Particle::from_position(1, 5.05, 5.0)
+        .with_velocity([-0.5, 0.0, 0.0])
+        .with_radius(0.1);
+    first.resolve_collision(&mut second, &SimulationConfig::default(), 1.0);
+    assert_eq!(first.velocity(), [-0.5, 0.0, 0.0]);
+    assert_eq!(second.velocity(), [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn elastic_collisions_conserve_momentum_and_energy() {
+    for seed in 0..1000 {
+        let (mut first, mut second) = random_pair(seed);
+        let before = momentum(&[first, second]);
+        let energy = kinetic_energy(&[first, second]);
+
+        first.resolve_collision(&mut second, &SimulationConfig::default(), 1.0);
+
+        assert_momentum(momentum(&[first, second]), before);
+        assert_close(kinetic_energy(&[first, second]), energy, "kinetic energy");
+    }
+}
+
+// Inelastic collisions still conserve momentum but lose energy, and leave the
+// pair separating at `restitution` times the speed they closed at
+#[test]
+fn restitution_scales_the_separation_speed() {
+    let closing_speed = |first: &Particle, second: &Particle| {
+        let (p1, p2) = (first.position(), second.position());
+        let (v1, v2) = (first.velocity(), second.velocity());
+        let distance = first.distance(second);
+        (0..3)
+            .map(|axis| (v2[axis] - v1[axis]) * (p2[axis] - p1[axis]))
+            .sum::<f32>()
+            / distance
+    };
+    for seed in 0..1000 {
+        let (mut first, mut second) = random_pair(seed);
+        let before = closing_speed(&first, &second);
+        let momentum_before = momentum(&[first, second]);
+        let energy = kinetic_energy(&[first, second]);
+
+        first.resolve_collision(&mut second, &SimulationConfig::default(), 0.5);
+
+        assert_momentum(momentum(&[first, second]), momentum_before);
+        assert!(kinetic_energy(&[first, second]) <= energy + TOLERANCE);
+        if before < 0.0 {
+            assert_close(closing_speed(&first, &second), -0.5 * before, "separation");
+        }
+    }
+}
+
+// Particles that are already separating keep their velocities, but are still
+// pushed apart until they no longer overlap
+#[test]
+fn separating_particles_are_only_pushed_apart() {
+    let mut first = Particle::from_position(0, 5.0, 5.0)
+        .with_velocity([-1.0, 0.0, 0.0])
+        .with_radius(0.5);
+    let mut second = Particle::from_position(1, 5.5, 5.0)
+        .with_velocity([1.0, 0.0, 0.0])
+        .with_radius(0.5);
+    first.resolve_collision(&mut second, &SimulationConfig::default(), 1.0);
+    assert_eq!(first.velocity(), [-1.0, 0.0, 0.0]);
+    assert_eq!(second.velocity(), [1.0, 0.0, 0.0]);
+    assert_close(first.distance(&second), 1.0, "distance");
+    assert_eq!(first.get_position(), (4.75, 5.0));
+}
+
+// A whole ballistic gas, in 2D and 3D: periodic walls move particles but
+// never change their velocities, so only collisions could change the totals
+#[test]
+fn elastic_gas_conserves_momentum_and_energy() {
+    for dimensions in [2, 3] {
+        let config = SimulationConfig {
+            dimensions,
+            movement: MovementModel::Ballistic,
+            boundary: Boundary::Periodic,
+            dt: 0.05,
+            radius: RadiusDistribution::Fixed(0.2),
+            restitution: Some(1.0),
+            ..seeded(7)
+        };
+        let mut system = ParticleSystem::new(config);
+        let before = momentum(system.particles());
+        let energy = kinetic_energy(system.particles());
+
+        let collisions = system.step(200);
+
+        assert!(collisions > 0);
+        let after = momentum(system.particles());
+        for axis in 0..3 {
+            assert!(
+                (after[axis] - before[axis]).abs() < 1e-3,
+                "momentum: {:?} != {:?}",
+                after,
+                before
+            );
+        }
+        assert_close(kinetic_energy(system.particles()), energy, "kinetic energy");
+    }
+}
@@ -0,0 +1,62 @@
+// Fixtures shared by the integration tests. Each test file uses only some of
+// them.
+#![allow(dead_code)]
+
+use particles::{
+    Frame, Interaction, InteractionRule, Particle, ParticleSystem, RadiusDistribution, Reaction,
+    SimulationConfig, Species, TrajectoryFormat, TrajectoryWriter,
+};
+
+// The default settings, seeded so every run of a test is the same
+pub fn seeded(seed: u64) -> SimulationConfig {
+    SimulationConfig {
+        seed: Some(seed),
+        ..SimulationConfig::default()
+    }
+}
+
+// A species of `count` particles with a fixed radius
+pub fn species(name: &str, count: usize, radius: f32) -> Species {
+    Species {
+        name: name.to_string(),
+        count,
+        radius: RadiusDistribution::Fixed(radius),
+        ..Species::default()
+    }
+}
+
+// A rule giving a pair of species an interaction without a reaction
+pub fn rule(a: &str, b: &str, interaction: Interaction) -> InteractionRule {
+    InteractionRule {
+        between: [a.to_string(), b.to_string()],
+        interaction,
+        reaction: None,
+    }
+}
+
+// A rule making a pair of species react
+pub fn reacting(a: &str, b: &str, reaction: Reaction) -> InteractionRule {
+    InteractionRule {
+        between: [a.to_string(), b.to_string()],
+        interaction: Interaction::React,
+        reaction: Some(reaction),
+    }
+}
+
+// A system holding exactly the given particles, at step 0
+pub fn system_of(config: SimulationConfig, particles: Vec<Particle>) -> ParticleSystem {
+    ParticleSystem::from_frame(config, Frame { step: 0, particles })
+}
+
+// The bytes of a trajectory of `frames`
+pub fn write_trajectory(
+    format: TrajectoryFormat,
+    frames: &[Frame],
+    config: &SimulationConfig,
+) -> Vec<u8> {
+    let mut writer = TrajectoryWriter::new(Vec::new(), format, config).unwrap();
+    for frame in frames {
+        writer.write(frame).unwrap();
+    }
+    writer.into_inner().unwrap()
+}
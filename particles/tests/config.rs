@@ -0,0 +1,56 @@
+// Loading, overriding and validating a SimulationConfig
+
+use particles::{RadiusDistribution, SimulationConfig, NUM_OF_PARTICLES};
+
+#[test]
+fn files_override_only_the_settings_they_give() {
+    let toml =
+        SimulationConfig::from_toml_str("num_of_particles = 50\nenclosure_size = 20.0\n").unwrap();
+    let json =
+        SimulationConfig::from_json_str(r#"{"num_of_particles": 50, "enclosure_size": 20.0}"#)
+            .unwrap();
+    assert_eq!(toml, json);
+    assert_eq!(toml.num_of_particles, 50);
+    assert_eq!(toml.enclosure_size, 20.0);
+    assert_eq!(toml.steps, SimulationConfig::default().steps);
+
+    assert_eq!(
+        SimulationConfig::from_toml_str("")
+            .unwrap()
+            .num_of_particles,
+        NUM_OF_PARTICLES
+    );
+    assert!(SimulationConfig::from_toml_str("particle_count = 5").is_err());
+    assert!(SimulationConfig::from_json_str("{").is_err());
+}
+
+#[test]
+fn overrides_accept_option_names() {
+    let mut config = SimulationConfig::default();
+    config.set("--particles", "7").unwrap();
+    config.set("--threshold", "0.5").unwrap();
+    config.set("enclosure-size", "4").unwrap();
+    assert_eq!(config.num_of_particles, 7);
+    assert_eq!(config.radius, RadiusDistribution::Fixed(0.25));
+    assert_eq!(config.enclosure_size, 4.0);
+    assert!(config.set("--particles", "many").is_err());
+    assert!(config.set("--colour", "red").is_err());
+}
+
+#[test]
+fn validation_rejects_impossible_settings() {
+    for (key, value) in [
+        ("--enclosure-size", "0"),
+        ("--enclosure-size", "-5"),
+        ("--threshold", "-0.2"),
+        ("--dimensions", "4"),
+        ("--dt", "0"),
+        ("--collision-workers", "0"),
+    ] {
+        let mut config = SimulationConfig::default();
+        config.set(key, value).unwrap();
+        assert!(config.validate().is_err(), "{} {}", key, value);
+    }
+    assert!(SimulationConfig::from_toml_str("enclosure_size = 0.0").is_err());
+    assert!(SimulationConfig::default().validate().is_ok());
+}
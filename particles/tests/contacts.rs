@@ -0,0 +1,59 @@
+// Collisions count pairs entering contact; overlaps count every step in contact
+
+mod common;
+
+use common::system_of;
+use particles::{Boundary, MovementModel, Particle, ParticleSystem, SimulationConfig};
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        movement: MovementModel::Ballistic,
+        boundary: Boundary::Periodic,
+        ..SimulationConfig::default()
+    }
+}
+
+fn system(particles: Vec<Particle>) -> ParticleSystem {
+    system_of(config(), particles)
+}
+
+// A pair resting in contact collides once, however long it stays
+#[test]
+fn resting_pair_collides_once() {
+    let mut system = system(vec![
+        Particle::from_position(0, 5.0, 5.0).with_radius(0.5),
+        Particle::from_position(1, 5.5, 5.0).with_radius(0.5),
+    ]);
+    assert_eq!(system.tick(), 1);
+    assert_eq!(system.step(49), 0);
+    assert_eq!(system.get_collision_count(), 1);
+    assert_eq!(system.get_overlap_count(), 50);
+}
+
+// A particle lapping the enclosure past one at rest collides on each pass,
+// and overlaps for every step of each pass
+#[test]
+fn each_pass_is_a_new_collision() {
+    let mut system = system(vec![
+        Particle::from_position(0, 5.0, 5.0).with_radius(0.5),
+        Particle::from_position(1, 1.0, 5.0)
+            .with_radius(0.5)
+            .with_velocity([0.25, 0.0, 0.0]),
+    ]);
+    let (mut passes, mut steps_in_contact, mut touching) = (0, 0, false);
+    for _ in 0..200 {
+        system.tick();
+        let [first, second] = system.particles() else {
+            panic!("the pair should survive");
+        };
+        let now = first.overlaps(second, system.config());
+        passes += (now && !touching) as usize;
+        steps_in_contact += now as usize;
+        touching = now;
+    }
+    // 200 steps at 0.25 a step is five laps of the 10-wide enclosure
+    assert_eq!(passes, 5);
+    assert_eq!(system.get_collision_count(), passes);
+    assert_eq!(system.get_overlap_count(), steps_in_contact);
+    assert!(steps_in_contact > 5 * 6);
+}
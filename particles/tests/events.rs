@@ -0,0 +1,92 @@
+// Collision events and the CSV and JSON Lines logs they are written to
+
+mod common;
+
+use common::system_of;
+use particles::{
+    CollisionEvent, EventFormat, EventWriter, MovementModel, Particle, SimulationConfig,
+};
+
+// The events of a pair at rest, which comes into contact once at step 1
+fn events() -> Vec<CollisionEvent> {
+    let particles = vec![
+        Particle::from_position(3, 4.0, 5.0).with_radius(0.5),
+        Particle::from_position(7, 4.5, 5.0).with_radius(0.5),
+    ];
+    let config = SimulationConfig {
+        movement: MovementModel::Ballistic,
+        ..SimulationConfig::default()
+    };
+    let mut system = system_of(config, particles);
+    let events = system.collision_events();
+    system.step(3);
+    system.stop_collision_events();
+    events.iter().collect()
+}
+
+fn write(format: EventFormat, events: &[CollisionEvent]) -> String {
+    let mut out = Vec::new();
+    let mut writer = EventWriter::new(&mut out, format).unwrap();
+    for event in events {
+        writer.write(event).unwrap();
+    }
+    writer.flush().unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn each_contact_gives_one_event() {
+    assert_eq!(
+        events(),
+        [CollisionEvent {
+            step: 1,
+            a: 3,
+            b: 7,
+            distance: 0.5,
+            midpoint: [4.25, 5.0, 0.0],
+        }]
+    );
+}
+
+#[test]
+fn csv_log_has_a_header_and_a_row_per_event() {
+    assert_eq!(
+        write(EventFormat::Csv, &events()),
+        "step,a,b,distance,midpoint_x,midpoint_y,midpoint_z\n1,3,7,0.5,4.25,5,0\n"
+    );
+    assert_eq!(
+        write(EventFormat::Csv, &[]),
+        "step,a,b,distance,midpoint_x,midpoint_y,midpoint_z\n"
+    );
+}
+
+#[test]
+fn json_lines_log_reads_back() {
+    let events = events();
+    let log = write(EventFormat::JsonLines, &events);
+    assert_eq!(
+        log,
+        "{\"step\":1,\"a\":3,\"b\":7,\"distance\":0.5,\"midpoint\":[4.25,5.0,0.0]}\n"
+    );
+    let read = log
+        .lines()
+        .map(|line| serde_json::from_str::<CollisionEvent>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(read, events);
+    assert_eq!(write(EventFormat::JsonLines, &[]), "");
+}
+
+#[test]
+fn format_follows_the_extension() {
+    assert_eq!(EventFormat::from_path("log.csv"), Some(EventFormat::Csv));
+    assert_eq!(
+        EventFormat::from_path("log.jsonl"),
+        Some(EventFormat::JsonLines)
+    );
+    assert_eq!(
+        EventFormat::from_path("out/log.ndjson"),
+        Some(EventFormat::JsonLines)
+    );
+    assert_eq!(EventFormat::from_path("log.txt"), None);
+    assert!(EventWriter::create("log.txt").is_err());
+}
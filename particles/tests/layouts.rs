@@ -0,0 +1,165 @@
+// Initial layouts: where particles start in the enclosure
+
+mod common;
+
+use std::fs;
+
+use common::{seeded, species};
+use particles::{Layout, Particle, ParticleSystem, RadiusDistribution, SimulationConfig};
+
+fn config(layout: Layout, dimensions: usize) -> SimulationConfig {
+    SimulationConfig {
+        dimensions,
+        layout,
+        ..seeded(5)
+    }
+}
+
+// Check no two particles of a new system overlap
+fn no_overlaps(system: &ParticleSystem) -> bool {
+    let particles = system.particles();
+    particles.iter().enumerate().all(|(i, a)| {
+        particles[i + 1..]
+            .iter()
+            .all(|b| !a.overlaps(b, system.config()))
+    })
+}
+
+#[test]
+fn lattice_puts_particles_in_the_middle_of_grid_cells() {
+    let system = ParticleSystem::new(config(Layout::Lattice, 2));
+    let mut positions = system.get_particle_positions();
+    positions.sort_by(|a, b| (a.1, a.0).partial_cmp(&(b.1, b.0)).unwrap());
+    assert_eq!(positions[0], (0.5, 0.5));
+    assert_eq!(positions[1], (1.5, 0.5));
+    assert_eq!(positions[10], (0.5, 1.5));
+    assert_eq!(positions[99], (9.5, 9.5));
+    assert_eq!(system.check_collisions(), 0);
+}
+
+// The species of a mixture are spread over a packed layout together, rather
+// than each filling a block of sites along the last axis
+#[test]
+fn packed_layouts_mix_species() {
+    for layout in [Layout::Lattice, Layout::Hexagonal] {
+        for dimensions in [2, 3] {
+            let config = SimulationConfig {
+                species: vec![species("a", 100, 0.1), species("b", 100, 0.1)],
+                ..config(layout.clone(), dimensions)
+            };
+            let system = ParticleSystem::new(config);
+            let height = |p: &Particle| p.position()[dimensions - 1];
+            let mut heights = system.particles().iter().map(height).collect::<Vec<_>>();
+            heights.sort_by(f32::total_cmp);
+            let median = heights[heights.len() / 2];
+            let low = system
+                .particles()
+                .iter()
+                .filter(|p| p.species() == 0 && height(p) < median)
+                .count();
+            assert!(
+                (25..=75).contains(&low),
+                "{} in {}D: {}",
+                layout,
+                dimensions,
+                low
+            );
+        }
+    }
+}
+
+#[test]
+fn packed_layouts_stay_inside_without_overlaps() {
+    for layout in [Layout::Lattice, Layout::Hexagonal, Layout::PoissonDisc] {
+        for dimensions in [2, 3] {
+            let config = SimulationConfig {
+                num_of_particles: 500,
+                radius: RadiusDistribution::Uniform {
+                    min: 0.05,
+                    max: 0.2,
+                },
+                ..config(layout.clone(), dimensions)
+            };
+            let system = ParticleSystem::new(config.clone());
+            assert_eq!(system.get_particle_count(), 500);
+            assert!(
+                system.particles().iter().all(|p| p.is_inside(&config)),
+                "{} in {}D",
+                layout,
+                dimensions
+            );
+            assert!(no_overlaps(&system), "{} in {}D", layout, dimensions);
+        }
+    }
+}
+
+#[test]
+fn clusters_gather_round_their_centres() {
+    let tight = ParticleSystem::new(config(
+        Layout::Clusters {
+            count: 1,
+            spread: 0.1,
+        },
+        2,
+    ));
+    let particles = tight.particles();
+    assert!(particles.iter().all(|p| p.distance(&particles[0]) < 1.0));
+    // Crowded together, they collide far more than uniform particles
+    let uniform = ParticleSystem::new(config(Layout::Uniform, 2));
+    assert!(tight.check_collisions() > 10 * uniform.check_collisions().max(1));
+}
+
+#[test]
+fn crowded_poisson_disc_is_an_error() {
+    let config = SimulationConfig {
+        radius: RadiusDistribution::Fixed(2.0),
+        ..config(Layout::PoissonDisc, 2)
+    };
+    assert!(ParticleSystem::try_new(config).is_err());
+}
+
+#[test]
+fn positions_load_from_a_csv_file() {
+    let path = std::env::temp_dir().join(format!("layout-{}.csv", std::process::id()));
+    fs::write(&path, "id,x,y\n0,1.0,2.0\n1,3.5,4.0\n").unwrap();
+    let config = SimulationConfig {
+        num_of_particles: 2,
+        ..config(Layout::File(path.clone()), 2)
+    };
+    let system = ParticleSystem::try_new(config.clone()).unwrap();
+    assert_eq!(
+        system.get_particle_positions(),
+        vec![(1.0, 2.0), (3.5, 4.0)]
+    );
+
+    // One row per particle, every one inside the enclosure
+    let more = SimulationConfig {
+        num_of_particles: 3,
+        ..config.clone()
+    };
+    assert!(ParticleSystem::try_new(more).is_err());
+    fs::write(&path, "x,y\n1.0,2.0\n30.0,4.0\n").unwrap();
+    assert!(ParticleSystem::try_new(config).is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn layout_forms_round_trip() {
+    for form in [
+        "uniform",
+        "lattice",
+        "hexagonal",
+        "clusters:4:0.5",
+        "poisson-disc",
+        "file:start.csv",
+    ] {
+        let layout: Layout = form.parse().unwrap();
+        assert_eq!(layout.to_string(), form);
+    }
+    assert!("clusters:4".parse::<Layout>().is_err());
+    assert!("clusters:0:0.5"
+        .parse::<Layout>()
+        .unwrap()
+        .validate()
+        .is_err());
+}
@@ -0,0 +1,61 @@
+// Per-particle radii: contact is overlap of the two spheres, whatever their sizes
+
+mod common;
+
+use common::seeded;
+use particles::{CollisionMethod, Particle, ParticleSystem, RadiusDistribution, SimulationConfig};
+
+#[test]
+fn particles_collide_when_they_overlap() {
+    let config = SimulationConfig::default();
+    let small = Particle::from_position(0, 5.0, 5.0).with_radius(0.1);
+    let large = Particle::from_position(1, 5.55, 5.0).with_radius(0.5);
+    assert!(small.collide(&large, &config));
+    assert!(!small.collide(&large.with_radius(0.4), &config));
+}
+
+// Cells are sized for the largest pair, so mixed sizes never slip past the grid
+#[test]
+fn mixed_sizes_grid_matches_brute_force() {
+    let distributions = [
+        RadiusDistribution::Uniform {
+            min: 0.01,
+            max: 0.5,
+        },
+        RadiusDistribution::Normal {
+            mean: 0.1,
+            std_dev: 0.1,
+        },
+    ];
+    for dimensions in [2, 3] {
+        for radius in distributions {
+            let grid = SimulationConfig {
+                dimensions,
+                radius,
+                ..seeded(11)
+            };
+            let brute_force = SimulationConfig {
+                collision_method: CollisionMethod::BruteForce,
+                ..grid.clone()
+            };
+            let mut grid = ParticleSystem::new(grid);
+            let mut brute_force = ParticleSystem::new(brute_force);
+            assert_eq!(
+                grid.step(100),
+                brute_force.step(100),
+                "{} in {}D",
+                radius,
+                dimensions
+            );
+        }
+    }
+}
+
+#[test]
+fn radius_forms_round_trip() {
+    for form in ["0.25", "uniform:0.1:0.3", "normal:0.2:0.05"] {
+        let radius: RadiusDistribution = form.parse().unwrap();
+        assert_eq!(radius.to_string(), form);
+    }
+    assert!("uniform:0.1".parse::<RadiusDistribution>().is_err());
+}
@@ -0,0 +1,108 @@
+// Collision rates over repeated runs, checked against the contact probability
+// of uniformly placed particles
+
+mod common;
+
+use std::f64::consts::PI;
+
+use common::{rule, seeded, species};
+use particles::{
+    analyse_rates, contact_probability, expected_contact_probability, Boundary, Cli, Interaction,
+    RunOptions, SimulationConfig, Strategy, Summary, PARTICLE_RADIUS,
+};
+
+fn config(boundary: Boundary, dimensions: usize) -> SimulationConfig {
+    SimulationConfig {
+        boundary,
+        dimensions,
+        steps: 200,
+        ..seeded(8)
+    }
+}
+
+#[test]
+fn summary_gives_mean_variance_and_interval() {
+    let summary = Summary::of(&[1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(summary.mean, 2.5);
+    assert!((summary.variance - 5.0 / 3.0).abs() < 1e-12);
+    // t with 3 degrees of freedom
+    let (low, high) = summary.confidence_interval();
+    let half_width = 3.182 * (5.0f64 / 12.0).sqrt();
+    assert!((low - (2.5 - half_width)).abs() < 1e-9);
+    assert!((high - (2.5 + half_width)).abs() < 1e-9);
+    assert_eq!(Summary::of(&[7.0]).confidence_interval(), (7.0, 7.0));
+}
+
+#[test]
+fn contact_probability_matches_closed_forms() {
+    let p = |r, dimensions, boundary| contact_probability(r, 1.0, dimensions, boundary).unwrap();
+    assert!((p(0.1, 2, Boundary::Periodic) - PI * 0.01).abs() < 1e-12);
+    // Two points in a unit square are within 1 of each other with probability pi - 13/6
+    assert!((p(1.0, 2, Boundary::Reflect) - (PI - 13.0 / 6.0)).abs() < 1e-12);
+    // Walls cut the ball short, so contact is less likely than on a torus
+    assert!(p(0.2, 3, Boundary::Reflect) < p(0.2, 3, Boundary::Periodic));
+    assert_eq!(contact_probability(0.6, 1.0, 2, Boundary::Periodic), None);
+}
+
+// The collision counter finds pairs in contact as often as theory says it should
+#[test]
+fn measured_contact_probability_agrees_with_theory() {
+    for (boundary, dimensions) in [(Boundary::Periodic, 2), (Boundary::Reflect, 3)] {
+        let config = config(boundary, dimensions);
+        let analysis = analyse_rates(&config, &RunOptions::new(Strategy::SingleLock), 20).unwrap();
+        assert_eq!(analysis.runs.len(), 20);
+        assert_eq!(analysis.runs[19].seed, 27);
+        let deviation = analysis.deviation().unwrap();
+        assert!(
+            deviation.abs() < 3.0,
+            "{} in {}D: {} standard errors",
+            boundary,
+            dimensions,
+            deviation
+        );
+    }
+}
+
+#[test]
+fn expectation_needs_uniform_particles() {
+    assert!(expected_contact_probability(&config(Boundary::Reflect, 2)).is_some());
+    assert_eq!(
+        expected_contact_probability(&config(Boundary::Clamp, 2)),
+        None
+    );
+    let bouncing = SimulationConfig {
+        restitution: Some(1.0),
+        ..config(Boundary::Periodic, 2)
+    };
+    assert_eq!(expected_contact_probability(&bouncing), None);
+}
+
+// Pairs that ignore each other never touch, but still count as pairs
+#[test]
+fn ignored_pairs_lower_the_expectation() {
+    let mixture = SimulationConfig {
+        species: vec![
+            species("a", 50, PARTICLE_RADIUS),
+            species("b", 50, PARTICLE_RADIUS),
+        ],
+        interactions: vec![rule("a", "b", Interaction::Ignore)],
+        ..config(Boundary::Periodic, 2)
+    };
+    let all = expected_contact_probability(&config(Boundary::Periodic, 2)).unwrap();
+    let mixed = expected_contact_probability(&mixture).unwrap();
+    // 2 * 1225 of the 4950 pairs can touch
+    assert!((mixed - all * 2450.0 / 4950.0).abs() < 1e-12);
+}
+
+#[test]
+fn runs_cannot_record_output() {
+    let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let cli = Cli::parse(args(&["--runs", "10"]), Strategy::SingleLock).unwrap();
+    assert_eq!(cli.runs, 10);
+    assert!(Cli::parse(
+        args(&["--runs", "10", "--events", "log.csv"]),
+        Strategy::SingleLock
+    )
+    .is_err());
+    assert!(Cli::parse(args(&["--runs", "0"]), Strategy::SingleLock).is_err());
+}
@@ -0,0 +1,170 @@
+// Reactions fired by collisions, and the population they leave behind
+
+mod common;
+
+use common::{reacting, seeded, species, system_of};
+use particles::{
+    Interaction, InteractionRule, MovementModel, Particle, ParticleSystem, Reaction,
+    SimulationConfig,
+};
+
+fn config(interactions: Vec<InteractionRule>) -> SimulationConfig {
+    SimulationConfig {
+        movement: MovementModel::Brownian,
+        species: vec![
+            species("a", 100, 0.15),
+            species("b", 100, 0.15),
+            species("c", 0, 0.15),
+        ],
+        interactions,
+        ..seeded(2)
+    }
+}
+
+// Ids stay unique and in order, and the population series follows the particles
+fn check_population(system: &ParticleSystem) {
+    let ids = system
+        .particles()
+        .iter()
+        .map(|particle| particle.id())
+        .collect::<Vec<_>>();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let counts = system.population().counts();
+    assert_eq!(counts.len() as u64, system.step_count() + 1);
+    let (step, last) = counts.last().unwrap();
+    assert_eq!(*step, system.step_count());
+    assert_eq!(last.iter().sum::<usize>(), system.get_particle_count());
+}
+
+#[test]
+fn merging_conserves_mass_and_momentum() {
+    let config = config(Vec::new());
+    let first = Particle::from_position(0, 5.0, 5.0)
+        .with_velocity([1.0, 0.0, 0.0])
+        .with_mass(1.0)
+        .with_radius(0.3);
+    let second = Particle::from_position(1, 5.4, 5.0)
+        .with_velocity([0.0, 2.0, 0.0])
+        .with_mass(3.0)
+        .with_radius(0.4);
+    let merged = first.merge(&second, 7, 2, &config);
+    assert_eq!((merged.id(), merged.species()), (7, 2));
+    assert_eq!(merged.mass(), 4.0);
+    assert_eq!(merged.momentum(), [1.0, 6.0, 0.0]);
+    assert!((merged.get_position().0 - 5.3).abs() < 1e-5);
+    assert!((merged.radius() - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn merging_and_annihilation_shrink_the_population() {
+    let mut system = ParticleSystem::new(config(vec![
+        reacting("a", "b", Reaction::Merge { into: "c".into() }),
+        reacting("c", "c", Reaction::Annihilate),
+    ]));
+    system.keep_population();
+    system.step(300);
+    check_population(&system);
+
+    let counts = system.population().counts();
+    let (_, last) = counts.last().unwrap();
+    // Every merge takes one a and one b
+    assert_eq!(last[0], last[1]);
+    assert!(last[0] < 100);
+    assert!(counts.iter().any(|(_, counts)| counts[2] > 0));
+    // Nothing is made from nothing
+    assert!(counts
+        .windows(2)
+        .all(|pair| pair[1].1.iter().sum::<usize>() <= pair[0].1.iter().sum::<usize>()));
+}
+
+// Without asking for the series only the first and latest rows are kept,
+// with the pairs summed over every step for the collision rates
+#[test]
+fn population_series_is_kept_only_when_asked() {
+    let rules = vec![reacting("a", "b", Reaction::Merge { into: "c".into() })];
+    let mut kept = ParticleSystem::new(config(rules.clone()));
+    kept.keep_population();
+    let mut bare = ParticleSystem::new(config(rules));
+    kept.step(100);
+    bare.step(100);
+
+    let (population, series) = (bare.population(), kept.population().counts());
+    assert!(population.counts().is_empty());
+    assert_eq!(population.first(), series.first());
+    assert_eq!(population.latest(), series.last());
+    let pairs = series[1..]
+        .iter()
+        .map(|(_, counts)| counts.iter().sum::<usize>() as f64)
+        .map(|n| n * (n - 1.0) / 2.0)
+        .sum::<f64>();
+    assert_eq!(population.pair_steps(), pairs);
+    assert_eq!(kept.population().pair_steps(), pairs);
+}
+
+#[test]
+fn spawning_adds_particles_with_new_ids() {
+    let spawn = Reaction::Spawn {
+        product: "c".into(),
+        count: 2,
+    };
+    let mut system = ParticleSystem::new(config(vec![reacting("a", "b", spawn)]));
+    system.keep_population();
+    system.step(50);
+    check_population(&system);
+
+    let (_, last) = system.population().counts().last().unwrap();
+    assert_eq!(&last[..2], &[100, 100]);
+    assert!(last[2] > 0 && last[2] % 2 == 0);
+    assert!(system
+        .particles()
+        .iter()
+        .filter(|particle| particle.species() == 2)
+        .all(|particle| particle.id() >= 200));
+}
+
+#[test]
+fn reactions_are_validated() {
+    let unknown = config(vec![reacting(
+        "a",
+        "b",
+        Reaction::Merge { into: "d".into() },
+    )]);
+    assert!(unknown.validate().is_err());
+
+    let mut missing = config(vec![reacting("a", "b", Reaction::Annihilate)]);
+    missing.interactions[0].reaction = None;
+    assert!(missing.validate().is_err());
+
+    let mut unused = config(vec![reacting("a", "b", Reaction::Annihilate)]);
+    unused.interactions[0].interaction = Interaction::Count;
+    assert!(unused.validate().is_err());
+}
+
+// A particle touching two partners at once reacts with only the first, and
+// its offspring, born on top of it, are not counted as colliding with it
+#[test]
+fn one_reaction_per_particle_per_step() {
+    let spawn = Reaction::Spawn {
+        product: "a".into(),
+        count: 1,
+    };
+    let config = SimulationConfig {
+        movement: MovementModel::Ballistic,
+        ..config(vec![reacting("a", "b", spawn)])
+    };
+    let at = |id, x, species| {
+        Particle::from_position(id, x, 5.0)
+            .with_species(species)
+            .with_radius(0.15)
+    };
+    let mut system = system_of(config, vec![at(0, 5.0, 0), at(1, 5.2, 1), at(2, 4.75, 1)]);
+    system.keep_population();
+
+    assert_eq!(system.finish_step(), 2);
+    assert_eq!(system.get_particle_count(), 4);
+    // The new particle still touches its parents, but nothing new happens
+    assert_eq!(system.finish_step(), 0);
+    assert_eq!(system.get_particle_count(), 4);
+    check_population(&system);
+}
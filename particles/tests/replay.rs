@@ -0,0 +1,189 @@
+// Reading trajectories back and replaying them
+
+mod common;
+
+use std::fs;
+
+use common::{seeded, write_trajectory};
+use particles::{
+    Frame, ParticleSystem, SimulationConfig, TrajectoryFormat, TrajectoryReader, TrajectoryWriter,
+};
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        num_of_particles: 40,
+        steps: 60,
+        ..seeded(4)
+    }
+}
+
+// Every frame of a run, and the run's collision total
+fn run(every: u64) -> (Vec<Frame>, usize) {
+    let mut system = ParticleSystem::new(config());
+    let frames = system.frames(every);
+    let total = system.step(60);
+    system.stop_frames();
+    (frames.iter().collect(), total)
+}
+
+fn read_back(format: TrajectoryFormat, frames: &[Frame]) -> Vec<Frame> {
+    let bytes = write_trajectory(format, frames, &config());
+    let mut reader = TrajectoryReader::new(&bytes[..], format).unwrap();
+    let frames = reader.by_ref().collect::<Result<_, _>>().unwrap();
+    assert_eq!(reader.species(), ["default"]);
+    frames
+}
+
+fn replay(frames: Vec<Frame>) -> ParticleSystem {
+    let mut frames = frames.into_iter();
+    let mut system = ParticleSystem::from_frame(config(), frames.next().unwrap());
+    for frame in frames {
+        system.replay_frame(frame);
+    }
+    system
+}
+
+#[test]
+fn every_format_reads_back_exactly() {
+    let (frames, _) = run(7);
+    for format in [
+        TrajectoryFormat::Csv,
+        TrajectoryFormat::Xyz,
+        TrajectoryFormat::Binary,
+    ] {
+        assert_eq!(read_back(format, &frames), frames, "{:?}", format);
+    }
+
+    let damaged = b"step,id,species\n";
+    assert!(TrajectoryReader::new(&damaged[..], TrajectoryFormat::Csv).is_err());
+}
+
+// Replaying every step finds the same collisions the run did
+#[test]
+fn replaying_every_step_matches_the_run() {
+    let (frames, total) = run(1);
+    let system = replay(read_back(TrajectoryFormat::Binary, &frames));
+    assert_eq!(system.step_count(), 60);
+    assert_eq!(system.get_collision_count(), total);
+
+    // Wider particles can only touch more often
+    let wider = frames
+        .iter()
+        .map(|frame| Frame {
+            step: frame.step,
+            particles: frame
+                .particles
+                .iter()
+                .map(|particle| particle.with_radius(1.0))
+                .collect(),
+        })
+        .collect();
+    assert!(replay(wider).get_overlap_count() > system.get_overlap_count());
+}
+
+#[test]
+fn differences_name_the_first_mismatch() {
+    let (frames, _) = run(10);
+    let names = ["default".to_string()];
+    let same = frames[2].clone();
+    assert_eq!(frames[2].difference(&names, &same, &names, 0.0), None);
+
+    let mut moved = frames[2].clone();
+    let [x, y, z] = moved.particles[3].position();
+    moved.particles[3].set_position([x + 1e-3, y, z]);
+    let difference = frames[2].difference(&names, &moved, &names, 0.0).unwrap();
+    assert!(difference.starts_with("particle 3 position"));
+    assert_eq!(frames[2].difference(&names, &moved, &names, 1e-2), None);
+
+    assert!(frames[1]
+        .difference(&names, &frames[2], &names, 0.0)
+        .unwrap()
+        .starts_with("step 10"));
+}
+
+// A frame with no particles left, say after every particle has reacted away,
+// keeps its step
+#[test]
+fn empty_frames_read_back() {
+    let (mut frames, _) = run(20);
+    frames[1].particles.clear();
+    for format in [
+        TrajectoryFormat::Csv,
+        TrajectoryFormat::Xyz,
+        TrajectoryFormat::Binary,
+    ] {
+        assert_eq!(read_back(format, &frames), frames, "{:?}", format);
+    }
+}
+
+#[test]
+fn compressed_files_read_back() {
+    let (frames, _) = run(20);
+    for name in ["csv.gz", "xyz.gz", "bin.gz"] {
+        let path = std::env::temp_dir().join(format!("replay-{}.{}", std::process::id(), name));
+        let mut writer = TrajectoryWriter::create(&path, &config()).unwrap();
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.into_inner().unwrap().close().unwrap();
+        // Every gzip stream starts with the same two bytes
+        assert_eq!(fs::read(&path).unwrap()[..2], [0x1f, 0x8b]);
+
+        let read = TrajectoryReader::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read, frames, "{}", name);
+    }
+}
+
+// Counts are read from the file, so a damaged count gives an error rather
+// than a huge allocation
+#[test]
+fn damaged_counts_are_errors() {
+    let (frames, _) = run(20);
+    let frames = &frames[..1];
+
+    let xyz =
+        String::from_utf8(write_trajectory(TrajectoryFormat::Xyz, frames, &config())).unwrap();
+    let damaged = xyz.replacen("40\n", &format!("{}\n", usize::MAX), 1);
+    let mut reader = TrajectoryReader::new(damaged.as_bytes(), TrajectoryFormat::Xyz).unwrap();
+    assert!(reader.next_frame().is_err());
+
+    let mut binary = write_trajectory(TrajectoryFormat::Binary, frames, &config());
+    let header = 4 + 4 + 4 + 4 + 4 + (4 + "default".len());
+    binary[header + 8..header + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+    let mut reader = TrajectoryReader::new(&binary[..], TrajectoryFormat::Binary).unwrap();
+    assert!(reader.next_frame().is_err());
+
+    binary[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(TrajectoryReader::new(&binary[..], TrajectoryFormat::Binary).is_err());
+}
+
+// Binary and XYZ files record the enclosure, so a replay can use it
+#[test]
+fn readers_give_the_recorded_enclosure() {
+    let deep = SimulationConfig {
+        dimensions: 3,
+        enclosure_size: 6.5,
+        ..config()
+    };
+    let frames = [ParticleSystem::new(deep.clone()).frame()];
+    for (format, recorded) in [
+        (TrajectoryFormat::Csv, None),
+        (TrajectoryFormat::Xyz, Some((3, 6.5))),
+        (TrajectoryFormat::Binary, Some((3, 6.5))),
+    ] {
+        let bytes = write_trajectory(format, &frames, &deep);
+        let mut reader = TrajectoryReader::new(&bytes[..], format).unwrap();
+        reader.next_frame().unwrap();
+        let enclosure = reader.dimensions().zip(reader.enclosure_size());
+        assert_eq!(enclosure, recorded, "{:?}", format);
+    }
+
+    let bytes = write_trajectory(TrajectoryFormat::Xyz, &frames, &config());
+    let mut reader = TrajectoryReader::new(&bytes[..], TrajectoryFormat::Xyz).unwrap();
+    reader.next_frame().unwrap();
+    assert_eq!(reader.dimensions(), Some(2));
+}
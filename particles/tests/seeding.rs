@@ -0,0 +1,61 @@
+// Seeded systems repeat themselves exactly
+
+mod common;
+
+use common::seeded;
+use particles::{MovementModel, ParticleSystem, SimulationConfig};
+
+fn config(move_workers: usize) -> SimulationConfig {
+    SimulationConfig {
+        num_of_particles: 200,
+        move_workers,
+        ..seeded(11)
+    }
+}
+
+// Each move worker draws from its own stream of the seed, so a split move
+// phase is as repeatable as a serial one
+#[test]
+fn same_seed_gives_same_positions() {
+    for move_workers in [1, 4] {
+        let (mut first, mut second) = (
+            ParticleSystem::new(config(move_workers)),
+            ParticleSystem::new(config(move_workers)),
+        );
+        assert_eq!(first.particles(), second.particles());
+        for _ in 0..50 {
+            assert_eq!(first.tick(), second.tick());
+            assert_eq!(
+                first.particles(),
+                second.particles(),
+                "{} workers",
+                move_workers
+            );
+        }
+        assert_eq!(first.get_collision_count(), second.get_collision_count());
+    }
+
+    let ballistic = SimulationConfig {
+        movement: MovementModel::Ballistic,
+        ..config(4)
+    };
+    let mut first = ParticleSystem::new(ballistic.clone());
+    let mut second = ParticleSystem::new(ballistic);
+    assert_eq!(first.step(50), second.step(50));
+    assert_eq!(first.particles(), second.particles());
+}
+
+#[test]
+fn different_seeds_give_different_positions() {
+    let mut first = ParticleSystem::new(config(4));
+    let mut second = ParticleSystem::new(SimulationConfig {
+        seed: Some(12),
+        ..config(4)
+    });
+    first.step(5);
+    second.step(5);
+    assert_ne!(
+        first.get_particle_positions(),
+        second.get_particle_positions()
+    );
+}
@@ -0,0 +1,99 @@
+// Saving a ParticleSystem and carrying on from the saved state
+
+mod common;
+
+use common::{reacting, seeded};
+use particles::{
+    CollisionRates, MovementModel, ParticleSystem, RadiusDistribution, Reaction, SimulationConfig,
+    Snapshot, SnapshotError, Species,
+};
+
+// A config that exercises every part of the state: worker streams, reactions
+// with new ids, and pairs staying in contact from one step to the next
+fn config() -> SimulationConfig {
+    let species = |name: &str, count| Species {
+        name: name.to_string(),
+        count,
+        radius: RadiusDistribution::Uniform { min: 0.1, max: 0.3 },
+        ..Species::default()
+    };
+    SimulationConfig {
+        movement: MovementModel::Brownian,
+        move_workers: 3,
+        species: vec![species("a", 80), species("b", 80), species("c", 0)],
+        interactions: vec![reacting(
+            "a",
+            "b",
+            Reaction::Merge {
+                into: "c".to_string(),
+            },
+        )],
+        ..seeded(21)
+    }
+}
+
+// Round trip through JSON, as saving to disk and loading again would
+fn reload(system: &ParticleSystem) -> ParticleSystem {
+    let mut json = Vec::new();
+    system.snapshot().write_json(&mut json).unwrap();
+    let snapshot = Snapshot::from_json_str(std::str::from_utf8(&json).unwrap()).unwrap();
+    assert_eq!(snapshot, system.snapshot());
+    ParticleSystem::from_snapshot(snapshot).unwrap()
+}
+
+#[test]
+fn resumed_runs_continue_bit_identically() {
+    let mut uninterrupted = ParticleSystem::new(config());
+    uninterrupted.step(300);
+
+    let mut first_half = ParticleSystem::new(config());
+    first_half.step(120);
+    let mut resumed = reload(&first_half);
+    resumed.step(180);
+
+    assert_eq!(resumed.step_count(), 300);
+    assert_eq!(resumed.snapshot(), uninterrupted.snapshot());
+    assert!(resumed.get_particle_count() < 160);
+}
+
+// Snapshots hold the first and latest population rows rather than the whole
+// series, which is still enough to measure the collision rates after resuming
+#[test]
+fn snapshots_leave_the_population_series_out() {
+    let mut uninterrupted = ParticleSystem::new(config());
+    uninterrupted.keep_population();
+    uninterrupted.step(300);
+    let snapshot = uninterrupted.snapshot();
+    assert!(snapshot.population.counts().is_empty());
+    assert_eq!(
+        snapshot.population.latest(),
+        uninterrupted.population().counts().last()
+    );
+
+    let mut first_half = ParticleSystem::new(config());
+    first_half.keep_population();
+    first_half.step(120);
+    let mut resumed = reload(&first_half);
+    resumed.keep_population();
+    resumed.step(180);
+    assert_eq!(resumed.population().counts().len(), 181);
+    assert_eq!(
+        CollisionRates::measure(&resumed),
+        CollisionRates::measure(&uninterrupted)
+    );
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let system = ParticleSystem::new(config());
+    let mut snapshot = system.snapshot();
+    snapshot.version += 1;
+    let mut json = Vec::new();
+    snapshot.write_json(&mut json).unwrap();
+    let text = String::from_utf8(json).unwrap();
+    assert!(matches!(
+        Snapshot::from_json_str(&text),
+        Err(SnapshotError::Version(_))
+    ));
+    assert!(ParticleSystem::from_snapshot(snapshot).is_err());
+}
@@ -0,0 +1,108 @@
+// Mixtures of species and the interaction matrix between them
+
+mod common;
+
+use common::{rule, seeded, species};
+use particles::{
+    Interaction, InteractionRule, MovementModel, Particle, ParticleSystem, SimulationConfig,
+};
+
+fn mixture(interactions: Vec<InteractionRule>) -> SimulationConfig {
+    SimulationConfig {
+        movement: MovementModel::Brownian,
+        species: vec![species("small", 150, 0.1), species("large", 30, 0.3)],
+        interactions,
+        ..seeded(11)
+    }
+}
+
+#[test]
+fn species_are_created_in_order_with_their_own_settings() {
+    let system = ParticleSystem::new(mixture(Vec::new()));
+    assert_eq!(system.get_particle_count(), 180);
+    for (id, particle) in system.particles().iter().enumerate() {
+        assert_eq!(particle.id(), id as u64);
+        let (species, radius) = if id < 150 { (0, 0.1) } else { (1, 0.3) };
+        assert_eq!(particle.species(), species);
+        assert_eq!(particle.radius(), radius);
+    }
+}
+
+// Ignored pairs are never counted, and the per-pair totals add up to the total
+#[test]
+fn collisions_are_counted_by_species_pair() {
+    let mut counted = ParticleSystem::new(mixture(Vec::new()));
+    let total = counted.step(200);
+    let by_pair = counted.get_species_collisions();
+    assert_eq!(by_pair.values().sum::<usize>(), total);
+    assert_eq!(by_pair.len(), 3);
+
+    let ignore = rule("large", "small", Interaction::Ignore);
+    let mut ignoring = ParticleSystem::new(mixture(vec![ignore]));
+    let total = ignoring.step(200);
+    let by_pair = ignoring.get_species_collisions();
+    assert_eq!(by_pair.values().sum::<usize>(), total);
+    assert!(!by_pair.contains_key(&("small".to_string(), "large".to_string())));
+    assert!(by_pair[&("small".to_string(), "small".to_string())] > 0);
+
+    let config = mixture(Vec::new());
+    let small = Particle::from_position(0, 5.0, 5.0).with_radius(0.1);
+    let large = Particle::from_position(1, 5.1, 5.0)
+        .with_radius(0.3)
+        .with_species(1);
+    assert!(small.collide(&large, &config));
+    assert!(!small.collide(&large, ignoring.config()));
+}
+
+#[test]
+fn mixtures_load_from_toml_and_are_validated() {
+    let config = SimulationConfig::from_toml_str(
+        r#"
+        [[species]]
+        name = "solvent"
+        count = 200
+
+        [[species]]
+        name = "solute"
+        count = 20
+        mass = 5.0
+        radius = { fixed = 0.3 }
+
+        [[interactions]]
+        between = ["solvent", "solvent"]
+        interaction = "ignore"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.species[1].mass, 5.0);
+    assert_eq!(config.interaction(0, 0), Interaction::Ignore);
+    assert_eq!(config.interaction(0, 1), Interaction::Count);
+
+    let unknown = mixture(vec![rule("small", "huge", Interaction::Ignore)]);
+    assert!(unknown.validate().is_err());
+    let mut twice = mixture(Vec::new());
+    twice.species.push(species("small", 1, 0.1));
+    assert!(twice.validate().is_err());
+}
+
+// A replay can meet a species its config does not list, such as the product
+// of a reaction
+#[test]
+fn species_can_be_added_later() {
+    let mut system = ParticleSystem::new(seeded(3));
+    system.keep_population();
+    system.step(2);
+    let particles = system.get_particle_count();
+    assert_eq!(system.add_species(species("product", 0, 0.2)), 1);
+    assert_eq!(system.add_species(species("default", 5, 0.2)), 0);
+    assert_eq!(system.config().species_name(1), "product");
+    assert_eq!(
+        system.population().final_counts(),
+        [
+            ("default".to_string(), particles),
+            ("product".to_string(), 0)
+        ]
+    );
+    system.step(2);
+    assert_eq!(system.population().counts()[4].1, [particles, 0]);
+}
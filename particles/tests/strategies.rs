@@ -0,0 +1,142 @@
+// Each concurrency strategy against a serial run of the same system
+
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use common::seeded;
+use particles::{run, ParticleSystem, RunOptions, RwFairness, SimulationConfig, Strategy};
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        steps: 200,
+        ..seeded(1)
+    }
+}
+
+// Run a strategy with `threads` move and collision threads, returning the
+// collision total
+fn run_strategy(options: RunOptions, threads: usize) -> usize {
+    let options = RunOptions {
+        move_threads: threads,
+        collision_threads: threads,
+        ..options
+    };
+    let system = Arc::new(Mutex::new(ParticleSystem::new(config())));
+    run(&system, &options).collisions
+}
+
+// Lock-free passes follow the moves, so while the total varies from run to
+// run it stays close to the serial one
+#[test]
+fn lock_free_total_is_close_to_serial() {
+    let serial = ParticleSystem::new(config()).step(200) as f64;
+    for threads in [1, 4] {
+        let total = run_strategy(RunOptions::new(Strategy::LockFree), threads) as f64;
+        assert!(
+            (total - serial).abs() < 0.25 * serial,
+            "{} threads: {} against {}",
+            threads,
+            total,
+            serial
+        );
+    }
+}
+
+// Every rwlock reader pass waits for its step, so all fairness policies count
+// what a serial run counts
+#[test]
+fn rwlock_matches_serial_with_every_fairness() {
+    let serial = ParticleSystem::new(config()).step(200);
+    for fairness in [
+        RwFairness::Platform,
+        RwFairness::WriterPriority,
+        RwFairness::Alternate,
+    ] {
+        let options = RunOptions {
+            fairness,
+            ..RunOptions::new(Strategy::RwLock)
+        };
+        assert_eq!(run_strategy(options, 3), serial, "{}", fairness);
+    }
+}
+
+// Strategies that check every step once before or while the next is moved
+// count exactly what a serial run counts
+#[test]
+fn deterministic_strategies_match_serial() {
+    let serial = ParticleSystem::new(config()).step(200);
+    for strategy in [
+        Strategy::SingleLock,
+        Strategy::Split,
+        Strategy::DoubleBuffer,
+    ] {
+        for threads in [1, 3] {
+            assert_eq!(
+                run_strategy(RunOptions::new(strategy), threads),
+                serial,
+                "{} with {} threads",
+                strategy,
+                threads
+            );
+        }
+    }
+}
+
+// Lock-free movers carry on the system's random streams, so a second run moves
+// the particles on from the first instead of repeating its moves, just as a
+// serial run with as many move workers would
+#[test]
+fn lock_free_runs_carry_on_their_streams() {
+    for movers in [1, 4] {
+        let config = SimulationConfig {
+            steps: 1,
+            move_workers: movers,
+            ..config()
+        };
+        let options = RunOptions {
+            move_threads: movers,
+            ..RunOptions::new(Strategy::LockFree)
+        };
+        let system = Arc::new(Mutex::new(ParticleSystem::new(config.clone())));
+        let positions = || system.lock().unwrap().get_particle_positions();
+        let offsets = |from: &[(f32, f32)], to: &[(f32, f32)]| {
+            from.iter()
+                .zip(to)
+                .map(|(a, b)| (b.0 - a.0, b.1 - a.1))
+                .collect::<Vec<_>>()
+        };
+
+        let start = positions();
+        run(&system, &options);
+        let middle = positions();
+        system.lock().unwrap().set_steps(2);
+        run(&system, &options);
+        let end = positions();
+        assert_ne!(offsets(&start, &middle), offsets(&middle, &end));
+
+        let mut serial = ParticleSystem::new(config);
+        serial.step(2);
+        assert_eq!(end, serial.get_particle_positions(), "{} movers", movers);
+    }
+}
+
+// A thread panicking ends a split run with its panic, rather than leaving the
+// other threads waiting for it forever
+#[test]
+fn split_run_passes_a_panic_on() {
+    let system = Arc::new(Mutex::new(ParticleSystem::new(config())));
+    system.lock().unwrap().on_frame(1, |frame| {
+        assert!(frame.step < 3, "failed at step {}", frame.step)
+    });
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let options = RunOptions::new(Strategy::Split);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run(&system, &options)));
+        sender.send(result.is_err()).unwrap();
+    });
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(30)), Ok(true));
+}
@@ -0,0 +1,79 @@
+// Recording frames of a run and writing them out as trajectories
+
+mod common;
+
+use common::{seeded, write_trajectory};
+use particles::{Frame, ParticleSystem, SimulationConfig, TrajectoryFormat, TRAJECTORY_MAGIC};
+
+fn config() -> SimulationConfig {
+    SimulationConfig {
+        num_of_particles: 10,
+        ..seeded(9)
+    }
+}
+
+// The frames of a 25-step run, every 10 steps
+fn frames() -> Vec<Frame> {
+    let mut system = ParticleSystem::new(config());
+    let frames = system.frames(10);
+    system.step(25);
+    system.stop_frames();
+    frames.iter().collect()
+}
+
+fn write(format: TrajectoryFormat, frames: &[Frame]) -> Vec<u8> {
+    write_trajectory(format, frames, &config())
+}
+
+#[test]
+fn frames_start_with_the_initial_state() {
+    let frames = frames();
+    let steps = frames.iter().map(|frame| frame.step).collect::<Vec<_>>();
+    assert_eq!(steps, [0, 10, 20]);
+    assert_eq!(
+        frames[0].particles,
+        ParticleSystem::new(config()).particles()
+    );
+    assert!(frames.iter().all(|frame| frame.particles.len() == 10));
+}
+
+#[test]
+fn formats_write_every_particle_of_every_frame() {
+    let frames = frames();
+
+    let csv = String::from_utf8(write(TrajectoryFormat::Csv, &frames)).unwrap();
+    let rows = csv.lines().collect::<Vec<_>>();
+    assert_eq!(rows[0], "step,id,species,mass,radius,x,y,z,vx,vy,vz");
+    assert_eq!(rows.len(), 1 + 30);
+    assert!(rows[11].starts_with("10,0,default,1,0.1,"));
+
+    let xyz = String::from_utf8(write(TrajectoryFormat::Xyz, &frames)).unwrap();
+    let lines = xyz.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3 * (2 + 10));
+    assert_eq!(lines[12], "10");
+    assert!(lines[13].contains("step=10"));
+    assert!(lines[14].starts_with("default "));
+
+    let binary = write(TrajectoryFormat::Binary, &frames);
+    assert_eq!(&binary[..4], TRAJECTORY_MAGIC);
+    let header = 4 + 4 + 4 + 4 + 4 + (4 + "default".len());
+    assert_eq!(binary.len(), header + 3 * (16 + 10 * 44));
+}
+
+#[test]
+fn formats_are_picked_by_extension() {
+    assert_eq!(
+        TrajectoryFormat::from_path("run.csv"),
+        Some(TrajectoryFormat::Csv)
+    );
+    assert_eq!(
+        TrajectoryFormat::from_path("run.extxyz.gz"),
+        Some(TrajectoryFormat::Xyz)
+    );
+    assert_eq!(
+        TrajectoryFormat::from_path("out/run.bin.gz"),
+        Some(TrajectoryFormat::Binary)
+    );
+    assert_eq!(TrajectoryFormat::from_path("run.gz"), None);
+    assert_eq!(TrajectoryFormat::from_path("run.txt"), None);
+}
@@ -0,0 +1,293 @@
+use particles::{
+    ConfigError, Frame, ParticleSystem, SimulationConfig, Species, TrajectoryInput,
+    TrajectoryReader,
+};
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "\
+Usage:
+    particles_replay stats FILE [OPTIONS]
+    particles_replay diff FILE FILE [--tolerance E]
+
+Replay a trajectory recorded with --trajectory. Files may be .csv, .xyz or .bin,
+optionally with .gz.
+
+stats: recount the collisions in every recorded frame and print totals.
+Collisions are found as the simulator finds them, so give the settings the
+run used: --config FILE and any of the simulator's options, such as
+--boundary or --collision-method. Binary and XYZ files record the number of
+dimensions and the enclosure size, which must then agree with any given; CSV
+files need --dimensions and --enclosure-size too. Contacts that start and end
+between two recorded frames are missed, so record with --trajectory-every 1
+to match the run's total. Frames are recorded after collisions are resolved
+and reactions fired, so a run with restitution or reactions replays to a
+lower total whatever the interval.
+    --radius R                 Give every particle radius R instead of its recorded one
+    --threshold DIST           Same as --radius DIST/2
+
+diff: compare two recordings frame by frame and report the first step where
+they diverge. Exits with status 1 if they do.
+    --tolerance E              Allow positions, velocities, masses and radii to
+                               differ by up to E (default 0, bit for bit)
+
+    -h, --help                 Show this help
+";
+
+// Open a trajectory, exiting with a message if it cannot be read
+fn open(path: &Path) -> TrajectoryReader<TrajectoryInput> {
+    TrajectoryReader::open(path).unwrap_or_else(|err| {
+        eprintln!("error: cannot read {}: {}", path.display(), err);
+        process::exit(1);
+    })
+}
+
+// Read the next frame, exiting with a message if the file is damaged
+fn next_frame(path: &Path, reader: &mut TrajectoryReader<TrajectoryInput>) -> Option<Frame> {
+    reader.next_frame().unwrap_or_else(|err| {
+        eprintln!("error: cannot read {}: {}", path.display(), err);
+        process::exit(1);
+    })
+}
+
+fn fail(err: ConfigError) -> ! {
+    eprintln!("error: {}\n\nRun with --help for usage.", err);
+    process::exit(2);
+}
+
+// Take the value of an option, given as `--key value` or `--key=value`
+fn value_of<I: Iterator<Item = String>>(arg: String, args: &mut I) -> (String, String) {
+    let (key, inline) = match arg.split_once('=') {
+        Some((key, value)) => (key.to_string(), Some(value.to_string())),
+        None => (arg, None),
+    };
+    match inline.or_else(|| args.next()) {
+        Some(value) => (key, value),
+        None => fail(ConfigError::MissingValue(key)),
+    }
+}
+
+fn parse_length(key: &str, value: &str) -> f32 {
+    match value.parse::<f32>() {
+        Ok(length) if length.is_finite() && length >= 0.0 => length,
+        _ => fail(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+// Map each species named in the file to the config's species
+fn species_map(config: &SimulationConfig, names: &[String]) -> Result<Vec<usize>, ConfigError> {
+    names
+        .iter()
+        .map(|name| {
+            config
+                .species_index(name)
+                .ok_or_else(|| unknown_species(name))
+        })
+        .collect()
+}
+
+fn unknown_species(name: &str) -> ConfigError {
+    ConfigError::Invalid(format!(
+        "species '{}' is not in the config; list every species it records",
+        name
+    ))
+}
+
+// Put a frame's particles into the config's species, and give them the
+// replay radius if there is one
+fn prepare(mut frame: Frame, species: &[usize], radius: Option<f32>) -> Frame {
+    for particle in frame.particles.iter_mut() {
+        let mut replayed = particle.with_species(species[particle.species()]);
+        if let Some(radius) = radius {
+            replayed = replayed.with_radius(radius);
+        }
+        *particle = replayed;
+    }
+    frame
+}
+
+fn stats<I: Iterator<Item = String>>(mut args: I) {
+    let mut path = None;
+    let mut config = SimulationConfig::default();
+    let mut config_file = false;
+    let mut radius = None;
+    let mut overrides = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            print!("{}", USAGE);
+            return;
+        }
+        if !arg.starts_with("--") {
+            match path {
+                None => path = Some(PathBuf::from(arg)),
+                Some(_) => fail(ConfigError::UnknownKey(arg)),
+            }
+            continue;
+        }
+        let (key, value) = value_of(arg, &mut args);
+        match key.as_str() {
+            "--config" => {
+                config = SimulationConfig::from_file(&value).unwrap_or_else(|e| fail(e));
+                config_file = true;
+            }
+            "--radius" => radius = Some(parse_length(&key, &value)),
+            "--threshold" => radius = Some(parse_length(&key, &value) / 2.0),
+            _ => overrides.push((key, value)),
+        }
+    }
+    let path = path.unwrap_or_else(|| fail(ConfigError::MissingValue("FILE".to_string())));
+
+    let mut reader = open(&path);
+    let first = next_frame(&path, &mut reader).unwrap_or_else(|| {
+        eprintln!("error: {} holds no frames", path.display());
+        process::exit(1);
+    });
+    // Binary and XYZ files record their enclosure. It stands in for the
+    // defaults, but a config or option that disagrees with it is an error.
+    let recorded = (reader.dimensions(), reader.enclosure_size());
+    if !config_file {
+        config.dimensions = recorded.0.unwrap_or(config.dimensions);
+        config.enclosure_size = recorded.1.unwrap_or(config.enclosure_size);
+    }
+    for (key, value) in overrides {
+        config.set(&key, &value).unwrap_or_else(|e| fail(e));
+    }
+    if let Some(dimensions) = recorded.0.filter(|&d| d != config.dimensions) {
+        fail(ConfigError::Invalid(format!(
+            "{} was recorded in {}D, not {}D",
+            path.display(),
+            dimensions,
+            config.dimensions
+        )));
+    }
+    if let Some(size) = recorded.1.filter(|&size| size != config.enclosure_size) {
+        fail(ConfigError::Invalid(format!(
+            "{} was recorded with enclosure size {}, not {}",
+            path.display(),
+            size,
+            config.enclosure_size
+        )));
+    }
+    // Binary files name their species up front; text files as they appear.
+    // Without species in the config, the file's own names are used, adding
+    // any that first appear in later frames, such as reaction products.
+    let listed = !config.species.is_empty();
+    let named = |name: &String| config.species_index(name).is_some();
+    if config.species.is_empty() && !reader.species().iter().all(named) {
+        config.species = reader
+            .species()
+            .iter()
+            .map(|name| Species {
+                name: name.clone(),
+                ..Species::default()
+            })
+            .collect();
+    }
+    config.validate().unwrap_or_else(|e| fail(e));
+    if config.restitution.is_some() || config.has_reactions() {
+        eprintln!(
+            "warning: frames are recorded after collisions are resolved and reactions fired, \
+             so the replay will count fewer collisions than the run did"
+        );
+    }
+    let mut species = species_map(&config, reader.species()).unwrap_or_else(|e| fail(e));
+
+    let mut system = ParticleSystem::from_frame(config, prepare(first, &species, radius));
+    let (mut frames, mut max_contacts) = (1u64, 0);
+    while let Some(frame) = next_frame(&path, &mut reader) {
+        while species.len() < reader.species().len() {
+            let name = reader.species()[species.len()].clone();
+            species.push(match system.config().species_index(&name) {
+                Some(index) => index,
+                None if !listed => system.add_species(Species {
+                    name,
+                    ..Species::default()
+                }),
+                None => fail(unknown_species(&name)),
+            });
+        }
+        let overlaps = system.get_overlap_count();
+        system.replay_frame(prepare(frame, &species, radius));
+        max_contacts = max_contacts.max(system.get_overlap_count() - overlaps);
+        frames += 1;
+    }
+
+    let replayed = (frames - 1).max(1) as f64;
+    println!("Frames: {}", frames);
+    println!("Last step: {}", system.step_count());
+    println!("Total collisions: {}", system.get_collision_count());
+    println!("Overlap frames: {}", system.get_overlap_count());
+    println!(
+        "Contacts per frame: {:.3} mean, {} max",
+        system.get_overlap_count() as f64 / replayed,
+        max_contacts
+    );
+    if system.config().species.len() > 1 {
+        for ((a, b), count) in system.get_species_collisions() {
+            println!("  {} + {}: {}", a, b, count);
+        }
+    }
+}
+
+fn diff<I: Iterator<Item = String>>(mut args: I) {
+    let mut paths = Vec::new();
+    let mut tolerance = 0.0;
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            print!("{}", USAGE);
+            return;
+        }
+        if !arg.starts_with("--") {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+        let (key, value) = value_of(arg, &mut args);
+        match key.as_str() {
+            "--tolerance" => tolerance = parse_length(&key, &value),
+            _ => fail(ConfigError::UnknownKey(key)),
+        }
+    }
+    if paths.len() != 2 {
+        fail(ConfigError::Invalid("diff needs two files".to_string()));
+    }
+
+    let (mut first, mut second) = (open(&paths[0]), open(&paths[1]));
+    let mut frames = 0;
+    loop {
+        let (a, b) = (
+            next_frame(&paths[0], &mut first),
+            next_frame(&paths[1], &mut second),
+        );
+        let difference = match (&a, &b) {
+            (None, None) => break,
+            (Some(a), None) => Some((a.step, format!("{} ends first", paths[1].display()))),
+            (None, Some(b)) => Some((b.step, format!("{} ends first", paths[0].display()))),
+            (Some(a), Some(b)) => a
+                .difference(first.species(), b, second.species(), tolerance)
+                .map(|difference| (a.step.min(b.step), difference)),
+        };
+        if let Some((step, difference)) = difference {
+            println!("Diverged at step {}: {}", step, difference);
+            process::exit(1);
+        }
+        frames += 1;
+    }
+    println!("Identical over {} frames", frames);
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("stats") => stats(args),
+        Some("diff") => diff(args),
+        Some("-h") | Some("--help") => print!("{}", USAGE),
+        Some(command) => fail(ConfigError::UnknownKey(command.to_string())),
+        None => {
+            eprint!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}